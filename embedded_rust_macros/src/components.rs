@@ -20,16 +20,21 @@ pub(crate) struct Components {
     pub channels: Channels,
     pub serials: Serials,
     pub timers: Timers,
+    /// Names of the user events
+    pub user_events: Vec<String>,
 }
 
 pub(crate) struct Sys {
-    pub identifiers: Vec<Ident>,
+    /// Constructor expressions of the sys resources
+    pub objects: Vec<Expr>,
     pub ty: Vec<Type>,
 }
 pub(crate) struct InPins {
     pub identifiers: Vec<Ident>,
     pub channels: Vec<Expr>,
     pub ports: Vec<Expr>,
+    /// Debounce time in ms
+    pub debounce: Vec<u64>,
     pub ty: Vec<Type>,
 }
 pub(crate) struct OutPins {
@@ -62,18 +67,21 @@ pub(crate) struct Timers {
 }
 
 pub(crate) fn parse_components(config: &Config) -> Components {
+    let sys_objects = crate::generate_sys_objects(config);
+    let sys_ty = parse_str("embedded_rust::resources::SysResource").unwrap();
     Components {
         init_stmts: config.init_statements(),
         interrupt_unmasks: config.interrupt_unmasks(),
         sys: Sys {
-            identifiers: vec![],
-            ty: vec![],
+            ty: vec![sys_ty; sys_objects.len()],
+            objects: sys_objects,
         },
         input_pins: InPins {
             identifiers: config.input_idents(),
             ty: config.input_tys(),
             channels: config.input_channels(),
             ports: config.input_ports(),
+            debounce: config.input_debounce_millis(),
         },
         output_pins: OutPins {
             identifiers: config.output_idents(),
@@ -103,5 +111,6 @@ pub(crate) fn parse_components(config: &Config) -> Components {
             parsed_data: (),
             ty: vec![],
         },
+        user_events: config.sys().user_events(),
     }
 }
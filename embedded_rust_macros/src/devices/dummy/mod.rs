@@ -9,7 +9,7 @@ use crate::types::{self, Direction, Gpio, Pin, PinMode, TriggerEdge};
 use quote::format_ident;
 use serde_derive::Deserialize;
 use syn::parse_str;
-use types::{PWMInterface, UnitHz};
+use types::{PWMInterface, UnitHz, UnitTime};
 
 /// The Generator struct is used to introduce all code generation functions.
 /// It has to implement the Generator trait.
@@ -31,6 +31,7 @@ pub struct DummyGpio(
     Direction,
     PinMode,
     #[serde(default)] Option<TriggerEdge>,
+    #[serde(default)] Option<(u64, UnitTime)>,
 );
 
 /// Pins should match the naming conventions of the device
@@ -79,6 +80,10 @@ impl SysGeneration for DummyGenerator {
     ) -> std::vec::Vec<syn::Stmt> {
         vec![]
     }
+    fn sys_clock_hertz(&self) -> syn::Expr {
+        // the dummy has no clock configuration, so just assume 8 MHz
+        syn::parse_quote!(8_000_000)
+    }
 }
 
 impl GpioGeneration for DummyGenerator {
@@ -117,6 +122,9 @@ impl Gpio for DummyGpio {
     fn trigger_edge(&self) -> std::option::Option<types::TriggerEdge> {
         self.3
     }
+    fn debounce(&self) -> std::option::Option<(u64, UnitTime)> {
+        self.4
+    }
 }
 
 impl Component for DummyGpio
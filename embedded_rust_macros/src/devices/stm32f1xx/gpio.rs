@@ -1,4 +1,4 @@
-use crate::types::{self, Direction, PinMode, TriggerEdge};
+use crate::types::{self, Direction, PinMode, TriggerEdge, UnitTime};
 use crate::Component;
 use quote::format_ident;
 use serde_derive::Deserialize;
@@ -10,6 +10,7 @@ pub struct StmGpio(
     Direction,
     PinMode,
     #[serde(default)] Option<TriggerEdge>,
+    #[serde(default)] Option<(u64, UnitTime)>,
 );
 
 impl types::Gpio for StmGpio {
@@ -25,6 +26,9 @@ impl types::Gpio for StmGpio {
     fn trigger_edge(&self) -> Option<TriggerEdge> {
         self.3
     }
+    fn debounce(&self) -> Option<(u64, UnitTime)> {
+        self.4
+    }
 }
 
 impl Component for StmGpio
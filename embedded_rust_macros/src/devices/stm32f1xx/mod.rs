@@ -162,6 +162,9 @@ impl SysGeneration for Generator {
         );
         clock
     }
+    fn sys_clock_hertz(&self) -> syn::Expr {
+        parse_quote!(clocks.sysclk().0)
+    }
 }
 
 impl GpioGeneration for Generator {
@@ -1,5 +1,5 @@
 use crate::{Frequency, Gpio, PWMInterface, types::Pin};
-use syn::{Stmt, Type};
+use syn::{Expr, Stmt, Type};
 
 /// The Generator trait is used to determine the proper generation functions
 /// It is just a meta trait that combines all special generation traits.
@@ -51,4 +51,8 @@ pub trait SysGeneration {
     /// With this function statements for board speed are generated
     /// These statements go right after the device init statements
     fn generate_clock(&self, sys_frequency: &Option<Frequency>) -> Vec<Stmt>;
+    /// An expression that evaluates to the core clock in hertz (as u32)
+    /// after the clock statements were executed.
+    /// The runtime derives its monotonic clock from this value.
+    fn sys_clock_hertz(&self) -> Expr;
 }
@@ -1,12 +1,15 @@
 mod components;
 mod devices;
 mod generation;
+mod task;
 mod types;
 // use embedded_rust::device::stm32f1xx::{Gpio, TriggerEdge};
 use components::*;
 use proc_macro::TokenStream;
 use quote::quote;
-use syn::{parse_macro_input, parse_quote, parse_str, Expr, ExprUnsafe, ItemStruct, Stmt};
+use syn::{
+    parse_macro_input, parse_quote, AttributeArgs, Expr, ExprUnsafe, ItemFn, ItemStruct, Stmt,
+};
 use types::*;
 
 #[proc_macro_attribute]
@@ -18,20 +21,27 @@ pub fn device_config(attr: TokenStream, item: TokenStream) -> TokenStream {
     let statiks = generate_component_statics(&components);
     let static_init = generate_static_init(&components);
     let heap_size = config.sys().heap_size();
+    let sys_clock = config.generator().sys_clock_hertz();
 
     let init_stmts = components.init_stmts;
     let interrupt_unmasks = components.interrupt_unmasks;
+    let start_watchdog = config.sys().watchdog_timeout_millis().map(|timeout| {
+        quote!(embedded_rust::watchdog::start(core::time::Duration::from_millis(#timeout));)
+    });
+    let configure_events = config.sys().events.as_ref().map(generate_event_queue);
     quote!(
         #strukt
         impl #struct_name{
             #[inline]
             fn init(){
+                #configure_events
                 #(#init_stmts)*
                 #(#statiks)*
                 #static_init
                 unsafe{
                     Runtime::init(
                         #heap_size,
+                        #sys_clock,
                         SYS_ARRAY.as_mut().unwrap(),
                         INPUT_ARRAY.as_mut().unwrap(),
                         OUTPUT_ARRAY.as_mut().unwrap(),
@@ -39,6 +49,7 @@ pub fn device_config(attr: TokenStream, item: TokenStream) -> TokenStream {
                         CHANNEL_ARRAY.as_mut().unwrap(),
                         SERIAL_ARRAY.as_mut().unwrap(),
                         TIMER_ARRAY.as_mut().unwrap(),
+                        USER_EVENTS.as_mut().unwrap(),
                     ).expect("Runtime initialization failed");
                 }
             }
@@ -53,6 +64,7 @@ pub fn device_config(attr: TokenStream, item: TokenStream) -> TokenStream {
                 unsafe{
                     #(#interrupt_unmasks)*
                 }
+                #start_watchdog
                 embedded_rust::Runtime::get().run()
             }
         }
@@ -61,6 +73,41 @@ pub fn device_config(attr: TokenStream, item: TokenStream) -> TokenStream {
     // quote!().into()
 }
 
+/// Places the futures of an async function in a static pool
+/// (requires the ``task-pool`` feature of the runtime):
+/// ```ignore
+/// #[task(pool_size = 2)]
+/// async fn blink(period: Duration) { ... }
+/// ```
+/// ``blink`` then returns ``Result<Task<()>, RuntimeError>``.
+/// With ``#[task(pool_size = 2, send)]`` it returns a ``SendTask`` for an
+/// ``InterruptExecutor`` instead, the future has to be ``Send``.
+#[proc_macro_attribute]
+pub fn task(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as AttributeArgs);
+    let function = parse_macro_input!(item as ItemFn);
+    task::generate_pooled_task(args, function).into()
+}
+
+/// The event buffer and the configuration of the event queue,
+/// before anything can push events
+fn generate_event_queue(events: &Events) -> Stmt {
+    let capacity = events.capacity();
+    let policy = match events.overflow {
+        EventOverflow::DropNewest => quote!(DropNewest),
+        EventOverflow::DropOldest => quote!(DropOldest),
+        EventOverflow::Coalesce => quote!(Coalesce),
+    };
+    parse_quote!({
+        static mut EVENT_BUFFER: [Option<embedded_rust::events::TimedEvent>; #capacity] =
+            [embedded_rust::events::NO_EVENT; #capacity];
+        embedded_rust::events::configure(
+            unsafe { &mut EVENT_BUFFER },
+            embedded_rust::events::OverflowPolicy::#policy,
+        );
+    })
+}
+
 fn generate_component_statics(components: &Components) -> Vec<Stmt> {
     let sys_tys = &components.sys.ty;
     let in_tys = &components.input_pins.ty;
@@ -70,13 +117,14 @@ fn generate_component_statics(components: &Components) -> Vec<Stmt> {
     let ser_tys = &components.serials.ty;
     let tim_tys = &components.serials.ty;
 
-    let sys_len = components.sys.identifiers.len();
+    let sys_len = components.sys.objects.len();
     let in_len = components.input_pins.identifiers.len();
     let out_len = components.output_pins.identifiers.len();
     let pwm_len = components.pwm_pins.identifiers.len();
     let chan_len = components.channels.identifiers.len();
     let ser_len = components.serials.identifiers.len();
     let tim_len = components.timers.identifiers.len();
+    let user_len = components.user_events.len();
 
     let statics: Vec<Stmt> = parse_quote!(
         // Tuple witch concrete objects
@@ -87,6 +135,7 @@ fn generate_component_statics(components: &Components) -> Vec<Stmt> {
         static mut CHANNELS: Option<(#(#chan_tys,)*)> = None;
         static mut SERIALS: Option<(#(#ser_tys,)*)> = None;
         static mut TIMERS: Option<(#(#tim_tys,)*)> = None;
+        static mut USER_EVENTS: Option<[embedded_rust::resources::UserEvent; #user_len]> = None;
 
         // Arrays with pointers to concrete objects
         static mut SYS_ARRAY: Option<[&'static mut dyn Resource;#sys_len]> = None;
@@ -101,7 +150,7 @@ fn generate_component_statics(components: &Components) -> Vec<Stmt> {
 }
 
 fn generate_static_init(components: &Components) -> ExprUnsafe {
-    let sys_idents = &components.sys.identifiers;
+    let sys_objects = &components.sys.objects;
     let in_idents = &components.input_pins.identifiers;
     let out_idents = &components.output_pins.identifiers;
     let pwm_idents = &components.pwm_pins.identifiers;
@@ -109,7 +158,7 @@ fn generate_static_init(components: &Components) -> ExprUnsafe {
     let serial_idents = &components.serials.identifiers;
     let timer_idents = &components.timers.identifiers;
 
-    let sys_index = (0..components.sys.identifiers.len()).map(syn::Index::from);
+    let sys_index = (0..components.sys.objects.len()).map(syn::Index::from);
     let in_index = (0..components.input_pins.identifiers.len()).map(syn::Index::from);
     let out_index = (0..components.output_pins.identifiers.len()).map(syn::Index::from);
     let pwm_index = (0..components.pwm_pins.identifiers.len()).map(syn::Index::from);
@@ -119,6 +168,7 @@ fn generate_static_init(components: &Components) -> ExprUnsafe {
 
     let in_channels = &components.input_pins.channels;
     let in_ports = &components.input_pins.ports;
+    let in_debounce = &components.input_pins.debounce;
 
     let out_channels = &components.output_pins.channels;
     let out_ports = &components.output_pins.ports;
@@ -126,16 +176,22 @@ fn generate_static_init(components: &Components) -> ExprUnsafe {
     let pwm_channels = &components.pwm_pins.channels;
     let pwm_ports = &components.pwm_pins.ports;
 
+    let user_index = (0..components.user_events.len()).map(|index| index as u8);
+    let user_names = &components.user_events;
+
     parse_quote!(
         unsafe{
-            //TODO: populate sys
-           SYS = Some((#(#sys_idents,)*));
-           INPUT_PINS = Some((#(InputPin::new(Pin::new(#in_channels , #in_ports), #in_idents),)*));
+           SYS = Some((#(#sys_objects,)*));
+           INPUT_PINS = Some((#(
+               InputPin::new(Pin::new(#in_channels , #in_ports), #in_idents)
+                   .with_debounce(core::time::Duration::from_millis(#in_debounce)),
+           )*));
            OUTPUT_PINS = Some((#(OutputPin::new(Pin::new(#out_channels, #out_ports), #out_idents),)*));
            PWM_PINS = Some((#(PWMPin::new(Pin::new(#pwm_channels, #pwm_ports), #pwm_idents),)*));
            CHANNELS = Some((#(#channel_idents,)*));
            SERIALS = Some((#(#serial_idents,)*));
            TIMERS = Some((#(#timer_idents,)*));
+           USER_EVENTS = Some([#(embedded_rust::resources::UserEvent::new(#user_index, #user_names),)*]);
 
             let sys = SYS.as_mut().unwrap();
             let input_pins = INPUT_PINS.as_mut().unwrap();
@@ -156,17 +212,20 @@ fn generate_static_init(components: &Components) -> ExprUnsafe {
     )
 }
 
-// TODO: integrate
-fn generate_sys_objects(config: &Config) -> Vec<Expr> {
-    let heapsize = config.sys().heap_size();
-    let sys_clock = config.sys().sys_clock();
-    let heap_obj = parse_str(&format!("Heap::new({})", heapsize)).unwrap();
-    if let Some(sys_clock) = sys_clock {
-        let sys_clock_obj = parse_str(&format!("SysClock::new({})", sys_clock)).unwrap();
-        vec![heap_obj, sys_clock_obj]
-    } else {
-        vec![heap_obj]
-    }
+/// The objects behind the ``sys:sys/...`` resources.
+/// The sys clock expression is only valid after the init statements.
+pub(crate) fn generate_sys_objects(config: &Config) -> Vec<Expr> {
+    let heap_size = config.sys().heap_size();
+    let sys_clock = config.generator().sys_clock_hertz();
+    vec![
+        parse_quote!(embedded_rust::resources::SysResource::new_heap(#heap_size)),
+        parse_quote!(embedded_rust::resources::SysResource::new_sysclock(#sys_clock as usize)),
+        parse_quote!(embedded_rust::resources::SysResource::new_tasks()),
+        parse_quote!(embedded_rust::resources::SysResource::new_queues()),
+        parse_quote!(embedded_rust::resources::SysResource::new_reset()),
+        parse_quote!(embedded_rust::resources::SysResource::new_deadlines()),
+        parse_quote!(embedded_rust::resources::SysResource::new_lost_events()),
+    ]
 }
 
 pub(crate) fn parse_json(attributes: &TokenStream) -> types::Config {
@@ -0,0 +1,112 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    spanned::Spanned, AttributeArgs, Error, FnArg, ItemFn, Lit, Meta, NestedMeta, ReturnType,
+};
+
+/// Turns an async function into a function that places its future in a
+/// static task pool with ``pool_size`` slots.
+/// The generated function has the same arguments and returns
+/// ``Result<Task<Output>, RuntimeError>``, or ``Result<SendTask<Output>, RuntimeError>``
+/// with the ``send`` argument.
+pub(crate) fn generate_pooled_task(args: AttributeArgs, function: ItemFn) -> TokenStream {
+    match pooled_task(args, function) {
+        Ok(tokens) => tokens,
+        Err(error) => error.to_compile_error(),
+    }
+}
+
+fn pooled_task(args: AttributeArgs, function: ItemFn) -> Result<TokenStream, Error> {
+    let TaskArgs { pool_size, send } = parse_args(&args)?;
+    let signature = &function.sig;
+    if signature.asyncness.is_none() {
+        return Err(Error::new(
+            signature.fn_token.span(),
+            "tasks have to be async functions",
+        ));
+    }
+    if !signature.generics.params.is_empty() {
+        return Err(Error::new(
+            signature.generics.span(),
+            "pooled tasks cannot be generic",
+        ));
+    }
+    // the inner function keeps the argument patterns,
+    // the outer one forwards the arguments by name
+    let mut outer_inputs = Vec::new();
+    let mut arg_names = Vec::new();
+    for (index, input) in signature.inputs.iter().enumerate() {
+        match input {
+            FnArg::Typed(arg) => {
+                let name = format_ident!("__arg{}", index);
+                let ty = &arg.ty;
+                outer_inputs.push(quote!(#name: #ty));
+                arg_names.push(name);
+            }
+            FnArg::Receiver(receiver) => {
+                return Err(Error::new(receiver.span(), "tasks cannot take self"))
+            }
+        }
+    }
+    let output = match &signature.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty),
+    };
+    let attrs = &function.attrs;
+    let vis = &function.vis;
+    let name = &signature.ident;
+    let mut inner = function.clone();
+    inner.attrs.clear();
+    inner.vis = syn::Visibility::Inherited;
+    inner.sig.ident = format_ident!("__{}_task", name);
+    let inner_name = &inner.sig.ident;
+    let (task, constructor) = if send {
+        (quote!(SendTask), quote!(send_task))
+    } else {
+        (quote!(Task), quote!(task))
+    };
+
+    Ok(quote!(
+        #(#attrs)*
+        #vis fn #name(#(#outer_inputs),*) -> Result<
+            embedded_rust::#task<#output>,
+            embedded_rust::RuntimeError,
+        > {
+            #inner
+            const SLOT_SIZE: usize = embedded_rust::pool::slot_size(&#inner_name);
+            static POOL: embedded_rust::pool::TaskPool<SLOT_SIZE, #pool_size> =
+                embedded_rust::pool::TaskPool::new();
+            POOL.#constructor(#inner_name(#(#arg_names),*))
+        }
+    ))
+}
+
+struct TaskArgs {
+    pool_size: usize,
+    /// The task is spawned on an interrupt executor
+    send: bool,
+}
+
+fn parse_args(args: &AttributeArgs) -> Result<TaskArgs, Error> {
+    let mut pool_size = None;
+    let mut send = false;
+    for arg in args {
+        match arg {
+            NestedMeta::Meta(Meta::NameValue(pair)) if pair.path.is_ident("pool_size") => {
+                match &pair.lit {
+                    Lit::Int(size) => pool_size = Some(size.base10_parse::<usize>()?),
+                    lit => return Err(Error::new(lit.span(), "expected an integer")),
+                }
+            }
+            NestedMeta::Meta(Meta::Path(path)) if path.is_ident("send") => send = true,
+            arg => return Err(Error::new(arg.span(), "unknown task argument")),
+        }
+    }
+    match pool_size {
+        Some(0) | None => Err(Error::new(
+            Span::call_site(),
+            "expected a pool size of at least one: #[task(pool_size = N)]",
+        )),
+        Some(pool_size) => Ok(TaskArgs { pool_size, send }),
+    }
+}
@@ -131,6 +131,16 @@ impl Config {
             .map(|gpio| gpio.identifier())
             .collect()
     }
+    /// Zero for input pins without debouncing
+    pub fn input_debounce_millis(&self) -> Vec<u64> {
+        self.input_pins()
+            .iter()
+            .map(|gpio| {
+                gpio.debounce()
+                    .map_or(0, |(value, unit)| unit.millis(value))
+            })
+            .collect()
+    }
     pub fn input_tys(&self) -> Vec<Type> {
         self.input_pins().iter().map(|gpio| gpio.ty()).collect()
     }
@@ -179,6 +189,8 @@ pub trait Gpio: Component {
     fn direction(&self) -> &Direction;
     fn mode(&self) -> &PinMode;
     fn trigger_edge(&self) -> Option<TriggerEdge>;
+    /// Edges within this time after an edge are dropped as bounces
+    fn debounce(&self) -> Option<(u64, UnitTime)>;
 }
 
 pub trait PWMInterface {
@@ -214,19 +226,98 @@ pub trait Pin {
 #[derive(Deserialize, Debug)]
 pub struct Sys {
     pub sys_clock: Option<(u32, UnitHz)>,
-    heap_size: (usize, UnitByte),
+    /// Without a heap size no heap is initialized.
+    /// That only works if nothing allocates, e.g. with the ``task-pool`` feature.
+    #[serde(default)]
+    heap_size: Option<(usize, UnitByte)>,
     pub log: Option<Log>,
+    /// The runtime starts and feeds the hardware watchdog if it is configured
+    #[serde(default)]
+    pub watchdog: Option<Watchdog>,
+    /// Capacity and overflow policy of the event queue
+    #[serde(default)]
+    pub events: Option<Events>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Watchdog {
+    pub timeout: (u64, UnitTime),
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Events {
+    #[serde(default = "Events::default_capacity")]
+    capacity: usize,
+    #[serde(default)]
+    pub overflow: EventOverflow,
+    /// Names of the software events behind ``event:user/<name>``
+    #[serde(default)]
+    pub user: Vec<String>,
+}
+
+impl Events {
+    /// Same as the runtime default
+    fn default_capacity() -> usize {
+        32
+    }
+    /// The runtime rejects an event buffer without capacity when it starts,
+    /// so it is rejected here already
+    pub fn capacity(&self) -> usize {
+        if self.capacity == 0 {
+            panic!("the event queue needs a capacity of at least one");
+        }
+        self.capacity
+    }
+}
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub enum EventOverflow {
+    #[serde(alias = "drop_newest", alias = "drop-newest")]
+    DropNewest,
+    #[serde(alias = "drop_oldest", alias = "drop-oldest")]
+    DropOldest,
+    #[serde(alias = "coalesce")]
+    Coalesce,
+}
+
+impl Default for EventOverflow {
+    fn default() -> Self {
+        EventOverflow::DropNewest
+    }
 }
 
 impl Sys {
     pub fn heap_size(&self) -> usize {
-        match self.heap_size.1 {
-            UnitByte::Byte => self.heap_size.0,
-            UnitByte::KB => self.heap_size.0 * 1024,
-            UnitByte::MB => self.heap_size.0 * 1024 * 1024,
-            UnitByte::GB => self.heap_size.0 * 1024 * 1024,
+        match self.heap_size {
+            None => 0,
+            Some((size, UnitByte::Byte)) => size,
+            Some((size, UnitByte::KB)) => size * 1024,
+            Some((size, UnitByte::MB)) => size * 1024 * 1024,
+            Some((size, UnitByte::GB)) => size * 1024 * 1024,
         }
     }
+    pub fn watchdog_timeout_millis(&self) -> Option<u64> {
+        self.watchdog
+            .as_ref()
+            .map(|watchdog| watchdog.timeout.1.millis(watchdog.timeout.0))
+    }
+    /// The names of the user events, they have to be unique path segments
+    pub fn user_events(&self) -> Vec<String> {
+        let names = self
+            .events
+            .as_ref()
+            .map(|events| events.user.clone())
+            .unwrap_or_default();
+        for (i, name) in names.iter().enumerate() {
+            if name.is_empty() || name.contains(|c| c == '/' || c == '?' || c == '#') {
+                panic!("invalid user event name {:?}", name);
+            }
+            if names[..i].contains(name) {
+                panic!("duplicate user event {:?}", name);
+            }
+        }
+        names
+    }
     pub fn sys_clock(&self) -> Option<usize> {
         self.sys_clock
             .as_ref()
@@ -257,10 +348,26 @@ pub enum UnitByte {
     #[serde(alias = "g", alias = "G", alias = "gb", alias = "GB")]
     GB,
 }
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub enum UnitTime {
+    #[serde(alias = "ms")]
+    Millisecond,
+    #[serde(alias = "s")]
+    Second,
+}
+
+impl UnitTime {
+    pub fn millis(self, value: u64) -> u64 {
+        match self {
+            UnitTime::Millisecond => value,
+            UnitTime::Second => value * 1000,
+        }
+    }
+}
 #[derive(Deserialize, Debug)]
 pub enum Log {
     // level: log::Level,
-// sink: uri
+    // sink: uri
 }
 
 pub struct Frequency(pub u32);
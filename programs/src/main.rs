@@ -6,7 +6,6 @@ extern crate alloc;
 // use cortex_m_semihosting::hprintln;
 
 use cortex_m_rt::entry;
-use embedded_rust::io::{AsyncReadExt, AsyncWriteExt};
 use embedded_rust::Task;
 use embedded_rust_macros::*;
 #[
@@ -36,16 +35,6 @@ fn main() -> ! {
     BluePill::run();
 }
 
-macro_rules! to_target_endianess {
-    ($int:expr) => {
-        if cfg!(target_endian = "big") {
-            $int.to_be_bytes()
-        } else {
-            $int.to_le_bytes()
-        }
-    };
-}
-
 enum Level {
     Full,
     High,
@@ -86,20 +75,24 @@ impl Brightness {
 }
 
 pub async fn test_task() {
-    let mut button_events = BluePill::get_resource("event:gpio/pa0").unwrap();
-    let mut led = BluePill::get_resource("digital:gpio/pc13").unwrap();
+    let mut button = BluePill::get_resource("event:gpio/pa0")
+        .unwrap()
+        .into_event()
+        .unwrap();
+    let mut led = BluePill::get_resource("digital:gpio/pc13")
+        .unwrap()
+        .into_digital()
+        .unwrap();
     let mut brightness = Brightness { level: Level::Off };
-    let mut pwm = BluePill::get_resource("percent:pwm/pa1").unwrap();
-    pwm.write(&to_target_endianess!(brightness.next()))
-        .await
+    let mut pwm = BluePill::get_resource("percent:pwm/pa1")
+        .unwrap()
+        .into_percent()
         .unwrap();
+    pwm.set(brightness.next()).await.unwrap();
     let mut led_state = false;
-    let mut buf = [0; 1];
-    while let Ok(_count) = button_events.read(&mut buf).await {
+    while let Ok(_timestamp) = button.next().await {
         led_state = !led_state;
-        led.write(&[led_state as u8]).await.unwrap();
-        pwm.write(&to_target_endianess!(brightness.next()))
-            .await
-            .unwrap();
+        led.set(led_state).await.unwrap();
+        pwm.set(brightness.next()).await.unwrap();
     }
 }
@@ -1,6 +1,24 @@
+use crate::events::{self, Event};
 use core::panic::PanicInfo;
+use cortex_m::interrupt::{self, CriticalSection, Nr};
+use cortex_m::peripheral::{syst::SystClkSource, NVIC, SYST};
 use cortex_m_rt::exception;
 
+/// Resolution of the monotonic clock (one tick per millisecond)
+pub const TICKS_PER_SECOND: u32 = 1000;
+
+/// Largest reload value of the 24 bit SysTick counter
+const MAX_RELOAD: u32 = 0x00FF_FFFF;
+
+/// Ticks since the clock was started
+static mut TICKS: u64 = 0;
+/// When TICKS reaches this value an Event::Timer is pushed
+static mut ALARM: u64 = u64::MAX;
+/// Core clock in hertz, it also drives the cycle counter
+static mut SYS_CLOCK: u32 = 0;
+/// Cycle counter at the start of the current tick
+static mut TICK_CYCLES: u32 = 0;
+
 #[global_allocator]
 static ALLOCATOR: linked_list_allocator::LockedHeap = linked_list_allocator::LockedHeap::empty();
 
@@ -12,7 +30,9 @@ fn alloc_error_handler(layout: alloc::alloc::Layout) -> ! {
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     // log::error!("panic: {}", info);
-    cortex_m_semihosting::hprintln!("panic: {}", info).unwrap();
+    // reset before semihosting, it faults without an attached debugger
+    crate::supervisor::panicked();
+    let _ = cortex_m_semihosting::hprintln!("panic: {}", info);
     cortex_m::interrupt::disable();
     loop {}
 }
@@ -35,3 +55,227 @@ fn DefaultHandler(irqn: i16) {
 pub(crate) fn init_heap(heap_bottom: usize, heap_size: usize) {
     unsafe { ALLOCATOR.lock().init(heap_bottom, heap_size) };
 }
+
+/// True if a tick of ``sys_clock`` cycles gives a SysTick reload
+/// between one and [MAX_RELOAD]
+pub(crate) fn valid_clock(sys_clock: u32) -> bool {
+    (1..=MAX_RELOAD).contains(&(sys_clock / TICKS_PER_SECOND).saturating_sub(1))
+}
+
+/// Configures the SysTick exception to fire ``TICKS_PER_SECOND`` times per second.
+/// The SysTick counter is clocked with the core clock ``sys_clock`` (in hertz).
+/// Also starts the DWT cycle counter. ``sys_clock`` has to be a [valid_clock].
+pub(crate) fn init_clock(sys_clock: u32) {
+    let mut peripherals = unsafe { cortex_m::Peripherals::steal() };
+    unsafe { SYS_CLOCK = sys_clock };
+    peripherals.DCB.enable_trace();
+    peripherals.DWT.enable_cycle_counter();
+    let syst = &mut peripherals.SYST;
+    syst.set_clock_source(SystClkSource::Core);
+    syst.set_reload(sys_clock / TICKS_PER_SECOND - 1);
+    syst.clear_current();
+    syst.enable_counter();
+    syst.enable_interrupt();
+}
+
+#[inline]
+pub(crate) fn now() -> u64 {
+    cortex_m::interrupt::free(|_| unsafe { TICKS })
+}
+
+/// True if called from an exception or interrupt handler
+#[inline]
+pub(crate) fn in_interrupt() -> bool {
+    use cortex_m::peripheral::{scb::VectActive, SCB};
+    SCB::vect_active() != VectActive::ThreadMode
+}
+
+/// The ticks plus the cycles since the start of the current tick.
+/// If the SysTick exception is pending (e.g. in a handler with the same
+/// priority) more than a tick of cycles passed, so the time still grows.
+#[inline]
+pub(crate) fn timestamp_micros() -> u64 {
+    interrupt::free(|_| unsafe {
+        TICKS * (1_000_000 / TICKS_PER_SECOND as u64)
+            + cycles_to_micros(cycles().wrapping_sub(TICK_CYCLES) as u64)
+    })
+}
+
+/// Records that the current tick started ``elapsed`` cycles ago
+#[inline]
+fn mark_tick(elapsed: u32) {
+    unsafe { TICK_CYCLES = cycles().wrapping_sub(elapsed) }
+}
+
+/// Core clock cycles counted by the DWT, wraps around
+#[inline]
+pub(crate) fn cycles() -> u32 {
+    cortex_m::peripheral::DWT::get_cycle_count()
+}
+
+#[inline]
+pub(crate) fn cycles_to_micros(cycles: u64) -> u64 {
+    match unsafe { SYS_CLOCK } {
+        0 => 0,
+        sys_clock => cycles * 1_000_000 / sys_clock as u64,
+    }
+}
+
+/// Push an Event::Timer as soon as the clock reaches ``tick``.
+/// Only one alarm is active, setting a new one overrides the old one.
+#[inline]
+pub(crate) fn set_alarm(tick: u64) {
+    cortex_m::interrupt::free(|_| unsafe { ALARM = tick })
+}
+
+/// The tick at which the alarm fires, ``u64::MAX`` if none is set
+#[inline]
+pub(crate) fn alarm() -> u64 {
+    interrupt::free(|_| unsafe { ALARM })
+}
+
+/// Adds ticks that passed while the SysTick exception was suppressed.
+/// The next tick starts right away.
+#[inline]
+pub(crate) fn advance_clock(ticks: u64) {
+    interrupt::free(|cs| {
+        mark_tick(0);
+        advance(ticks, cs)
+    })
+}
+
+fn advance(ticks: u64, cs: &CriticalSection) {
+    unsafe {
+        TICKS += ticks;
+        if TICKS >= ALARM {
+            ALARM = u64::MAX;
+            events::push(Event::Timer, cs);
+        }
+    }
+}
+
+#[exception]
+fn SysTick() {
+    interrupt::free(|cs| {
+        mark_tick(0);
+        advance(1, cs)
+    })
+}
+
+/// Implemented priority bits of the NVIC, they are the upper bits of the
+/// priority byte
+const NVIC_PRIO_BITS: u8 = 4;
+pub(crate) const INTERRUPT_PRIORITIES: u8 = 1 << NVIC_PRIO_BITS;
+
+/// An interrupt only known by its number
+#[derive(Clone, Copy)]
+struct Irq(u8);
+unsafe impl Nr for Irq {
+    #[inline]
+    fn nr(&self) -> u8 {
+        self.0
+    }
+}
+
+/// ``priority`` has to be below [INTERRUPT_PRIORITIES]
+pub(crate) fn enable_interrupt(nr: u8, priority: u8) {
+    debug_assert!(priority < INTERRUPT_PRIORITIES);
+    let mut nvic = unsafe { cortex_m::Peripherals::steal() }.NVIC;
+    unsafe {
+        nvic.set_priority(Irq(nr), priority << (8 - NVIC_PRIO_BITS));
+        NVIC::unmask(Irq(nr));
+    }
+}
+
+#[inline]
+pub(crate) fn pend_interrupt(nr: u8) {
+    NVIC::pend(Irq(nr))
+}
+
+/// Sleeps with WFI until an interrupt occurs or the clock reaches the alarm.
+/// The SysTick exceptions in between are suppressed: the counter is
+/// reprogrammed to expire at the alarm (at most ``MAX_RELOAD`` cycles) and
+/// the skipped ticks are added after the wakeup.
+/// Returns right away if ``pending`` is true once interrupts are masked.
+pub(crate) fn idle_tickless(pending: fn() -> bool) {
+    let mut syst = unsafe { cortex_m::Peripherals::steal() }.SYST;
+    interrupt::disable();
+    if pending() {
+        unsafe { interrupt::enable() };
+        return;
+    }
+    let tick_cycles = SYST::get_reload() + 1;
+    let idle_ticks = unsafe { ALARM.saturating_sub(TICKS) };
+    let ticks = idle_ticks.min((MAX_RELOAD / tick_cycles) as u64) as u32;
+    if ticks < 2 {
+        // the next tick is due anyway
+        cortex_m::asm::wfi();
+        unsafe { interrupt::enable() };
+        return;
+    }
+    syst.disable_counter();
+    // a tick that expired in the meantime is pending and counted by SysTick
+    syst.has_wrapped();
+    // cycles left in the current tick
+    let current = SYST::get_current();
+    let sleep_cycles = current + (ticks - 1) * tick_cycles;
+    syst.set_reload(sleep_cycles - 1);
+    syst.clear_current();
+    syst.enable_counter();
+    // with interrupts masked, WFI still returns on a pending interrupt,
+    // the handler runs as soon as they are enabled again
+    cortex_m::asm::wfi();
+    syst.disable_counter();
+    let (skipped, remaining) = if syst.has_wrapped() {
+        // the alarm is due, the pending SysTick counts the last tick
+        ((ticks - 1) as u64, tick_cycles)
+    } else {
+        // woken early by another interrupt
+        let elapsed = sleep_cycles - 1 - SYST::get_current();
+        if elapsed < current {
+            (0, current - elapsed)
+        } else {
+            let since = elapsed - current;
+            (
+                (1 + since / tick_cycles) as u64,
+                tick_cycles - since % tick_cycles,
+            )
+        }
+    };
+    // finish the current tick, the counter loads the regular reload value
+    // when it expires (the new reload value is only loaded on a wrap)
+    syst.set_reload(remaining.max(2) - 1);
+    syst.clear_current();
+    syst.enable_counter();
+    syst.set_reload(tick_cycles - 1);
+    interrupt::free(|cs| {
+        mark_tick(tick_cycles - remaining);
+        advance(skipped, cs)
+    });
+    unsafe { interrupt::enable() };
+}
+
+/// Sleeps in the exception and interrupt handlers:
+/// if a handler returns, the core goes back to sleep instead of resuming
+/// the executor unless the handler called [wake_thread].
+/// Returns right away if ``pending`` is true once interrupts are masked.
+pub(crate) fn idle_on_exit(pending: fn() -> bool) {
+    let mut scb = unsafe { cortex_m::Peripherals::steal() }.SCB;
+    interrupt::disable();
+    if !pending() {
+        scb.set_sleeponexit();
+        // the first sleep happens with interrupts masked, so no handler can
+        // clear the flag between the check and the sleep
+        cortex_m::asm::wfi();
+    }
+    unsafe { interrupt::enable() };
+}
+
+/// Resumes the executor after the current interrupt handler returns.
+/// Only needed with sleep-on-exit, a no-op otherwise.
+#[inline]
+pub fn wake_thread() {
+    unsafe { cortex_m::Peripherals::steal() }
+        .SCB
+        .clear_sleeponexit()
+}
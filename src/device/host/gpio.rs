@@ -0,0 +1,66 @@
+//! Pins of the simulated device.
+//! They are named like the pins of the STM32F1 (``pa0`` to ``pe15``),
+//! so the same resource URIs can be used on the host.
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, PartialOrd, Ord, Hash)]
+pub enum Channel {
+    A,
+    B,
+    C,
+    D,
+    E,
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug, PartialOrd, Ord, Hash)]
+pub enum Port {
+    P00,
+    P01,
+    P02,
+    P03,
+    P04,
+    P05,
+    P06,
+    P07,
+    P08,
+    P09,
+    P10,
+    P11,
+    P12,
+    P13,
+    P14,
+    P15,
+}
+
+impl core::fmt::Display for Channel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Channel::A => write!(f, "a"),
+            Channel::B => write!(f, "b"),
+            Channel::C => write!(f, "c"),
+            Channel::D => write!(f, "d"),
+            Channel::E => write!(f, "e"),
+        }
+    }
+}
+impl core::fmt::Display for Port {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Port::P00 => write!(f, "0"),
+            Port::P01 => write!(f, "1"),
+            Port::P02 => write!(f, "2"),
+            Port::P03 => write!(f, "3"),
+            Port::P04 => write!(f, "4"),
+            Port::P05 => write!(f, "5"),
+            Port::P06 => write!(f, "6"),
+            Port::P07 => write!(f, "7"),
+            Port::P08 => write!(f, "8"),
+            Port::P09 => write!(f, "9"),
+            Port::P10 => write!(f, "10"),
+            Port::P11 => write!(f, "11"),
+            Port::P12 => write!(f, "12"),
+            Port::P13 => write!(f, "13"),
+            Port::P14 => write!(f, "14"),
+            Port::P15 => write!(f, "15"),
+        }
+    }
+}
@@ -0,0 +1,277 @@
+//! A simulated device for the build host (``host`` feature).
+//!
+//! It runs the same [Runtime](crate::Runtime), executor and event queue
+//! with std, so tasks and resources can be tested with ``cargo test``.
+//! Interrupts are simulated on the calling thread with [simulate_interrupt]
+//! or [trigger]. The clock does not follow the wall clock: it only advances
+//! with [advance_clock] or if the runtime waits for the next timer, e.g. in
+//! [Runtime::run_until_idle](crate::Runtime::run_until_idle).
+//!
+//! ```ignore
+//! #[test]
+//! fn blinks() {
+//!     let _simulation = Simulation::start();
+//!     let rt = Runtime::init(
+//!         0, 0, &mut [], &mut [], &mut [], &mut [], &mut [], &mut [], &mut [], &mut [],
+//!     )
+//!     .unwrap();
+//!     let handle = Task::new(blink(Duration::from_millis(500))).spawn();
+//!     rt.run_for(Duration::from_secs(2));
+//!     assert!(!handle.is_finished());
+//! }
+//! ```
+mod gpio;
+
+use crate::events::{self, Event, TimedEvent};
+use crate::power::IdlePolicy;
+use crate::resources::{gpio::Pin, Resources};
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+pub use gpio::*;
+use std::sync::{Mutex, MutexGuard};
+
+/// Resolution of the simulated clock (one tick per millisecond)
+pub const TICKS_PER_SECOND: u32 = 1000;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+/// When TICKS reaches this value an Event::Timer is pushed
+static ALARM: AtomicU64 = AtomicU64::new(u64::MAX);
+/// Watchdog timeout in ticks, zero if it is not started
+static WATCHDOG_TIMEOUT: AtomicU64 = AtomicU64::new(0);
+static WATCHDOG_FED: AtomicU64 = AtomicU64::new(0);
+/// Set while a simulated interrupt handler runs
+static IN_INTERRUPT: AtomicBool = AtomicBool::new(false);
+/// Set by a simulated reset
+static RESETTING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Ord, PartialOrd)]
+pub enum ExtiEvent {
+    Gpio(Pin),
+}
+
+#[cfg(feature = "trace")]
+impl ExtiEvent {
+    /// Lower 12 bits of the trace event code: channel and port of the pin
+    pub(crate) fn trace_code(&self) -> u16 {
+        match self {
+            ExtiEvent::Gpio(pin) => (pin.channel() as u16) << 4 | pin.port() as u16,
+        }
+    }
+}
+
+/// False if the resource of the event dropped it
+pub fn handle_exti_event(event: &ExtiEvent, timed: &TimedEvent) -> bool {
+    match event {
+        ExtiEvent::Gpio(pin) => match Resources::get_input_pin(pin) {
+            Ok(resource) => resource.handle_event(timed),
+            Err(_) => true,
+        },
+    }
+}
+
+/// Critical sections only have to exclude simulated interrupts,
+/// which run on the same thread.
+pub mod interrupt {
+    pub use cortex_m::interrupt::{CriticalSection, Mutex, Nr};
+
+    #[inline]
+    pub fn free<F, R>(f: F) -> R
+    where
+        F: FnOnce(&CriticalSection) -> R,
+    {
+        f(unsafe { &CriticalSection::new() })
+    }
+}
+
+/// Exclusive access to the simulated device.
+/// The runtime, its queues and the clock are global, so tests that use them
+/// have to run one after another. Starting a simulation waits for the
+/// previous one to end and resets the runtime, the queues and the clock.
+pub struct Simulation {
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl Simulation {
+    pub fn start() -> Self {
+        static LOCK: Mutex<()> = Mutex::new(());
+        // a failed test poisons the lock, the state is reset anyway
+        let lock = LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        crate::Runtime::reset();
+        crate::executor::reset();
+        events::reset();
+        crate::watchdog::reset();
+        WATCHDOG_TIMEOUT.store(0, Ordering::SeqCst);
+        TICKS.store(0, Ordering::SeqCst);
+        ALARM.store(u64::MAX, Ordering::SeqCst);
+        IN_INTERRUPT.store(false, Ordering::SeqCst);
+        RESETTING.store(false, Ordering::SeqCst);
+        Simulation { _lock: lock }
+    }
+}
+
+/// Runs ``handler`` as if it was an interrupt handler
+pub fn simulate_interrupt<R>(handler: impl FnOnce(&interrupt::CriticalSection) -> R) -> R {
+    let nested = IN_INTERRUPT.swap(true, Ordering::SeqCst);
+    let result = interrupt::free(handler);
+    IN_INTERRUPT.store(nested, Ordering::SeqCst);
+    result
+}
+
+/// Pushes an external interrupt event like the EXTI handlers of a device,
+/// ``high`` is the level of the pin right after the edge
+pub fn trigger(event: ExtiEvent, high: bool) {
+    simulate_interrupt(|cs| events::push_level(Event::ExternalInterrupt(event), high, cs))
+}
+
+/// Advances the clock by ``duration`` and fires the alarm if it is reached
+pub fn advance_clock(duration: core::time::Duration) {
+    advance_to(now() + crate::time::duration_to_ticks(duration))
+}
+
+/// Sets the clock to ``tick`` (if it is in the future) and fires the alarm
+/// if it is reached. Panics if the watchdog expired in the meantime.
+pub(crate) fn advance_to(tick: u64) {
+    simulate_interrupt(|cs| {
+        TICKS.fetch_max(tick, Ordering::SeqCst);
+        let timeout = WATCHDOG_TIMEOUT.load(Ordering::SeqCst);
+        if timeout != 0 && now() - WATCHDOG_FED.load(Ordering::SeqCst) > timeout {
+            panic!("watchdog reset")
+        }
+        if now() >= ALARM.load(Ordering::SeqCst) {
+            ALARM.store(u64::MAX, Ordering::SeqCst);
+            events::push(Event::Timer, cs);
+        }
+    })
+}
+
+/// The tick at which the alarm fires, ``u64::MAX`` if none is set
+#[inline]
+pub(crate) fn alarm() -> u64 {
+    ALARM.load(Ordering::SeqCst)
+}
+
+/// The host uses the global allocator of std
+pub(crate) fn init_heap(_heap_bottom: usize, _heap_size: usize) {}
+
+#[inline]
+pub fn heap_bottom() -> usize {
+    0
+}
+
+#[inline]
+pub fn sleep() {
+    std::thread::yield_now()
+}
+
+/// The clock is simulated, so every core clock is fine
+#[inline]
+pub(crate) fn valid_clock(_sys_clock: u32) -> bool {
+    true
+}
+
+/// Nothing to configure, the clock is simulated
+pub(crate) fn init_clock(_sys_clock: u32) {}
+
+#[inline]
+pub(crate) fn now() -> u64 {
+    TICKS.load(Ordering::SeqCst)
+}
+
+#[inline]
+pub(crate) fn in_interrupt() -> bool {
+    IN_INTERRUPT.load(Ordering::SeqCst)
+}
+
+/// Event timestamps follow the simulated clock
+#[inline]
+pub(crate) fn timestamp_micros() -> u64 {
+    now() * (1_000_000 / TICKS_PER_SECOND as u64)
+}
+
+/// Nanoseconds of the wall clock, wraps around
+#[inline]
+pub(crate) fn cycles() -> u32 {
+    static START: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
+    START
+        .get_or_init(std::time::Instant::now)
+        .elapsed()
+        .as_nanos() as u32
+}
+
+#[inline]
+pub(crate) fn cycles_to_micros(cycles: u64) -> u64 {
+    cycles / 1000
+}
+
+#[inline]
+pub(crate) fn set_alarm(tick: u64) {
+    ALARM.store(tick, Ordering::SeqCst)
+}
+
+/// Jumps to the next timer deadline instead of sleeping.
+/// Without a timer only another thread could provide work,
+/// so the thread just sleeps for a tick.
+pub(crate) fn idle(_policy: &IdlePolicy, pending: fn() -> bool) {
+    if pending() {
+        return;
+    }
+    match alarm() {
+        u64::MAX => std::thread::sleep(std::time::Duration::from_millis(1)),
+        tick => advance_to(tick),
+    }
+}
+
+static RESET_RECORD: Mutex<Option<(u16, u32)>> = Mutex::new(None);
+
+pub(crate) fn write_reset_record(code: u16, value: u32) {
+    *RESET_RECORD.lock().unwrap() = Some((code, value))
+}
+
+pub(crate) fn take_reset_record() -> Option<(u16, u32)> {
+    RESET_RECORD.lock().unwrap().take()
+}
+
+/// The simulation cannot reset, the test fails instead.
+/// The panic is not isolated like the panics of tasks, see [resetting].
+pub(crate) fn system_reset() -> ! {
+    RESETTING.store(true, Ordering::SeqCst);
+    panic!("device reset")
+}
+
+/// True once [system_reset] was called, a panic of a task is resumed then
+#[inline]
+pub(crate) fn resetting() -> bool {
+    RESETTING.load(Ordering::SeqCst)
+}
+
+pub(crate) fn start_watchdog(timeout: core::time::Duration) {
+    WATCHDOG_FED.store(now(), Ordering::SeqCst);
+    WATCHDOG_TIMEOUT.store(
+        crate::time::duration_to_ticks(timeout).max(1),
+        Ordering::SeqCst,
+    );
+}
+
+#[inline]
+pub(crate) fn feed_watchdog() {
+    WATCHDOG_FED.store(now(), Ordering::SeqCst)
+}
+
+/// The simulation never resets
+#[inline]
+pub(crate) fn take_watchdog_reset() -> bool {
+    false
+}
+
+/// Priorities are checked like on the device
+pub(crate) const INTERRUPT_PRIORITIES: u8 = 16;
+
+/// Interrupt executors run between two polls of the thread-mode executor,
+/// there is no interrupt controller to configure
+pub(crate) fn enable_interrupt(_nr: u8, _priority: u8) {}
+
+#[inline]
+pub(crate) fn pend_interrupt(_nr: u8) {}
+
+/// There is no sleep-on-exit on the host
+#[inline]
+pub fn wake_thread() {}
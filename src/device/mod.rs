@@ -1,3 +1,7 @@
+#[cfg(all(feature = "stm32f1xx", feature = "host"))]
+compile_error!("select a single device: disable the default features to use \"host\"");
+
+#[cfg(feature = "stm32f1xx")]
 mod cortex_m;
 
 #[cfg(feature = "stm32f1xx")]
@@ -5,8 +9,23 @@ mod stm32f1xx;
 #[cfg(feature = "stm32f1xx")]
 pub(crate) use self::cortex_m::init_heap;
 #[cfg(feature = "stm32f1xx")]
+pub use self::cortex_m::TICKS_PER_SECOND;
+#[cfg(feature = "stm32f1xx")]
 use stm32f1xx as dev;
 
+#[cfg(feature = "host")]
+pub mod host;
+#[cfg(feature = "host")]
+pub(crate) use self::host::init_heap;
+#[cfg(feature = "host")]
+pub use self::host::TICKS_PER_SECOND;
+#[cfg(feature = "host")]
+use host as dev;
+
+/// Critical sections (``free``) and the ``Mutex`` they unlock.
+/// On the host they only exclude simulated interrupts.
+pub use dev::interrupt;
+
 pub type ExtiEvent = dev::ExtiEvent;
 pub type Channel = dev::Channel;
 pub type Port = dev::Port;
@@ -27,7 +46,120 @@ pub fn sleep() {
     dev::sleep()
 }
 
+/// Sleeps until the next interrupt or timer deadline as selected by ``policy``.
+/// Returns right away if ``pending`` is true once interrupts are masked.
+#[inline]
+pub(crate) fn idle(policy: &crate::power::IdlePolicy, pending: fn() -> bool) {
+    dev::idle(policy, pending)
+}
+
+/// Resumes the executor after the current interrupt handler returns,
+/// see [IdlePolicy::SleepOnExit](crate::power::IdlePolicy::SleepOnExit)
+#[inline]
+pub fn wake_thread() {
+    dev::wake_thread()
+}
+
+/// Stores a reset reason (``code`` and ``value``) that survives the next reset
+#[inline]
+pub(crate) fn write_reset_record(code: u16, value: u32) {
+    dev::write_reset_record(code, value)
+}
+
+/// The record written before the last reset, it is cleared afterwards
+#[inline]
+pub(crate) fn take_reset_record() -> Option<(u16, u32)> {
+    dev::take_reset_record()
+}
+
+#[inline]
+pub(crate) fn system_reset() -> ! {
+    dev::system_reset()
+}
+
+/// Starts the hardware watchdog, it resets the device if it is not fed
+/// within ``timeout``
+#[inline]
+pub(crate) fn start_watchdog(timeout: core::time::Duration) {
+    dev::start_watchdog(timeout)
+}
+
+#[inline]
+pub(crate) fn feed_watchdog() {
+    dev::feed_watchdog()
+}
+
+/// True if the watchdog caused the last reset
+#[inline]
+pub(crate) fn take_watchdog_reset() -> bool {
+    dev::take_watchdog_reset()
+}
+
+/// Number of NVIC priority levels, zero is the highest priority
+pub(crate) const INTERRUPT_PRIORITIES: u8 = dev::INTERRUPT_PRIORITIES;
+
+/// Sets the NVIC priority of interrupt ``nr`` (zero is the highest) and
+/// unmasks it, ``priority`` has to be below [INTERRUPT_PRIORITIES]
+#[inline]
+pub(crate) fn enable_interrupt(nr: u8, priority: u8) {
+    dev::enable_interrupt(nr, priority)
+}
+
+/// Sets interrupt ``nr`` pending, its handler runs as soon as its priority allows
+#[inline]
+pub(crate) fn pend_interrupt(nr: u8) {
+    dev::pend_interrupt(nr)
+}
+
+/// True if the core clock in hertz can drive the monotonic clock
+#[inline]
+pub(crate) fn valid_clock(sys_clock: u32) -> bool {
+    dev::valid_clock(sys_clock)
+}
+
+/// Starts the monotonic clock with the given core clock in hertz,
+/// it has to be a [valid_clock]
+#[inline]
+pub(crate) fn init_clock(sys_clock: u32) {
+    dev::init_clock(sys_clock)
+}
+
+/// Ticks of the monotonic clock since it was started
+#[inline]
+pub(crate) fn now() -> u64 {
+    dev::now()
+}
+
+/// True if called from an exception or interrupt handler
+#[inline]
+pub(crate) fn in_interrupt() -> bool {
+    dev::in_interrupt()
+}
+
+/// Microseconds since the monotonic clock was started, for event timestamps
+#[inline]
+pub(crate) fn timestamp_micros() -> u64 {
+    dev::timestamp_micros()
+}
+
+/// Core clock cycles for fine grained measurements, wraps around
+#[inline]
+pub(crate) fn cycles() -> u32 {
+    dev::cycles()
+}
+
+#[inline]
+pub(crate) fn cycles_to_micros(cycles: u64) -> u64 {
+    dev::cycles_to_micros(cycles)
+}
+
+/// The device pushes an ``Event::Timer`` when the monotonic clock reaches ``tick``
+#[inline]
+pub(crate) fn set_alarm(tick: u64) {
+    dev::set_alarm(tick)
+}
+
 #[inline]
-pub fn handle_exti_event(event: &ExtiEvent) {
-    dev::handle_exti_event(event)
+pub fn handle_exti_event(event: &ExtiEvent, timed: &crate::events::TimedEvent) -> bool {
+    dev::handle_exti_event(event, timed)
 }
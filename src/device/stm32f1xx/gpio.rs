@@ -1,5 +1,6 @@
 use crate::events::{self, Event};
 use crate::{device::ExtiEvent, resources::gpio::Pin};
+use embedded_hal::digital::v2::InputPin;
 use stm32f1xx_hal::device::interrupt;
 use stm32f1xx_hal::gpio::{gpioa, gpiob, gpioc, gpiod, gpioe, ExtiPin, Floating, Input};
 
@@ -110,7 +111,10 @@ macro_rules! check_interrupt {
         if pin.check_interrupt() {
             let e = Event::ExternalInterrupt(ExtiEvent::Gpio(Pin::new($channel, $port)));
             cortex_m::interrupt::free(|cs| {
-                events::push(e, cs);
+                // sampled with the timestamp, the pin may change again
+                // before the executor handles the edge
+                let high = pin.is_high().unwrap_or(false);
+                events::push_level(e, high, cs);
                 pin.clear_interrupt_pending_bit();
             });
         }
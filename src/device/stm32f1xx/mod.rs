@@ -1,12 +1,23 @@
 #[macro_use]
 mod usart;
 mod gpio;
+mod power;
 mod pwm;
+mod watchdog;
+pub use crate::device::cortex_m::wake_thread;
+pub(crate) use crate::device::cortex_m::{
+    cycles, cycles_to_micros, enable_interrupt, in_interrupt, init_clock, now, pend_interrupt,
+    set_alarm, timestamp_micros, valid_clock, INTERRUPT_PRIORITIES,
+};
+use crate::events::TimedEvent;
 use crate::resources::gpio::Pin;
 use crate::resources::Resources;
+pub use ::cortex_m::interrupt;
 pub use gpio::*;
+pub(crate) use power::{idle, system_reset, take_reset_record, write_reset_record};
 pub use pwm::*;
 pub use usart::*;
+pub(crate) use watchdog::{feed_watchdog, start_watchdog, take_watchdog_reset};
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Ord, PartialOrd)]
 pub enum ExtiEvent {
@@ -17,17 +28,32 @@ pub enum ExtiEvent {
     EthernetWakeup,
 }
 
-pub fn handle_exti_event(event: &ExtiEvent) {
-    match event {
-        ExtiEvent::Gpio(pin) => {
-            if let Ok(resource) = Resources::get_input_pin(pin) {
-                resource.handle_event()
-            }
+#[cfg(feature = "trace")]
+impl ExtiEvent {
+    /// Lower 12 bits of the trace event code: channel and port of a pin,
+    /// other lines start at 0x100
+    pub(crate) fn trace_code(&self) -> u16 {
+        match self {
+            ExtiEvent::Gpio(pin) => (pin.channel() as u16) << 4 | pin.port() as u16,
+            ExtiEvent::Pvd => 0x100,
+            ExtiEvent::RtcAlarm => 0x101,
+            ExtiEvent::UsbWakeup => 0x102,
+            ExtiEvent::EthernetWakeup => 0x103,
         }
-        ExtiEvent::Pvd => {}
-        ExtiEvent::RtcAlarm => {}
-        ExtiEvent::UsbWakeup => {}
-        ExtiEvent::EthernetWakeup => {}
+    }
+}
+
+/// False if the resource of the event dropped it
+pub fn handle_exti_event(event: &ExtiEvent, timed: &TimedEvent) -> bool {
+    match event {
+        ExtiEvent::Gpio(pin) => match Resources::get_input_pin(pin) {
+            Ok(resource) => resource.handle_event(timed),
+            Err(_) => true,
+        },
+        ExtiEvent::Pvd => true,
+        ExtiEvent::RtcAlarm => true,
+        ExtiEvent::UsbWakeup => true,
+        ExtiEvent::EthernetWakeup => true,
     }
 }
 
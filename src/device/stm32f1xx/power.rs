@@ -0,0 +1,248 @@
+//! STOP mode with RTC alarm wakeups.
+//!
+//! The RTC is clocked by the LSI and counts with ``TICKS_PER_SECOND``, so
+//! the time spent in STOP mode can be added to the monotonic clock.
+//! The LSI is not trimmed (30 to 60 kHz), long STOP phases therefore drift.
+//! Register addresses and bits are taken from the reference manual RM0008.
+use crate::device::cortex_m::{advance_clock, alarm, idle_on_exit, idle_tickless, now};
+use crate::device::TICKS_PER_SECOND;
+use crate::power::IdlePolicy;
+use crate::time::duration_to_ticks;
+use core::ptr::{read_volatile, write_volatile};
+use cortex_m::peripheral::NVIC;
+use stm32f1xx_hal::device::{interrupt, Interrupt};
+
+const RCC_CR: usize = 0x4002_1000;
+const RCC_CFGR: usize = 0x4002_1004;
+const RCC_APB1ENR: usize = 0x4002_101C;
+const RCC_BDCR: usize = 0x4002_1020;
+pub(super) const RCC_CSR: usize = 0x4002_1024;
+const PWR_CR: usize = 0x4000_7000;
+const RTC_CRH: usize = 0x4000_2800;
+const RTC_CRL: usize = 0x4000_2804;
+const RTC_PRLH: usize = 0x4000_2808;
+const RTC_PRLL: usize = 0x4000_280C;
+const RTC_CNTH: usize = 0x4000_2818;
+const RTC_CNTL: usize = 0x4000_281C;
+const RTC_ALRH: usize = 0x4000_2820;
+const RTC_ALRL: usize = 0x4000_2824;
+const BKP_DR1: usize = 0x4000_6C04;
+const BKP_DR2: usize = 0x4000_6C08;
+const BKP_DR3: usize = 0x4000_6C0C;
+const BKP_DR4: usize = 0x4000_6C10;
+const EXTI_IMR: usize = 0x4001_0400;
+const EXTI_RTSR: usize = 0x4001_0408;
+const EXTI_PR: usize = 0x4001_0414;
+
+const RCC_CR_HSEON: u32 = 1 << 16;
+const RCC_CR_HSERDY: u32 = 1 << 17;
+const RCC_CR_PLLON: u32 = 1 << 24;
+const RCC_CR_PLLRDY: u32 = 1 << 25;
+const RCC_CFGR_SW: u32 = 0b11;
+const RCC_APB1ENR_BKPEN: u32 = 1 << 27;
+const RCC_APB1ENR_PWREN: u32 = 1 << 28;
+const RCC_BDCR_RTCSEL_LSI: u32 = 0b10 << 8;
+const RCC_BDCR_RTCEN: u32 = 1 << 15;
+const RCC_CSR_LSION: u32 = 1 << 0;
+const RCC_CSR_LSIRDY: u32 = 1 << 1;
+const PWR_CR_LPDS: u32 = 1 << 0;
+const PWR_CR_PDDS: u32 = 1 << 1;
+const PWR_CR_DBP: u32 = 1 << 8;
+const RTC_CRH_ALRIE: u32 = 1 << 1;
+const RTC_CRL_ALRF: u32 = 1 << 1;
+const RTC_CRL_RSF: u32 = 1 << 3;
+const RTC_CRL_CNF: u32 = 1 << 4;
+const RTC_CRL_RTOFF: u32 = 1 << 5;
+/// The RTC alarm is connected to EXTI line 17
+const EXTI_RTC_ALARM: u32 = 1 << 17;
+
+const LSI_HERTZ: u32 = 40_000;
+/// Marks a valid reset record in the backup registers
+const RESET_RECORD_MAGIC: u32 = 0x5E5E;
+
+/// Set once the RTC runs
+static mut RTC_READY: bool = false;
+
+/// Idles until the next interrupt or timer deadline as selected by ``policy``.
+/// Returns right away if ``pending`` is true once interrupts are masked.
+pub(crate) fn idle(policy: &IdlePolicy, pending: fn() -> bool) {
+    match policy {
+        IdlePolicy::WaitForEvent => {
+            if !pending() {
+                cortex_m::asm::wfe()
+            }
+        }
+        IdlePolicy::WaitForInterrupt => idle_tickless(pending),
+        IdlePolicy::SleepOnExit => idle_on_exit(pending),
+        IdlePolicy::Stop { min_idle } => {
+            if alarm().saturating_sub(now()) < duration_to_ticks(*min_idle) {
+                idle_tickless(pending)
+            } else {
+                stop(pending)
+            }
+        }
+    }
+}
+
+/// The reset record is kept in the backup registers ``DR1`` to ``DR4``,
+/// they keep their content during a system reset.
+pub(crate) fn write_reset_record(code: u16, value: u32) {
+    unsafe {
+        enable_backup_domain();
+        write(BKP_DR2, code as u32);
+        write(BKP_DR3, value & 0xFFFF);
+        write(BKP_DR4, value >> 16);
+        write(BKP_DR1, RESET_RECORD_MAGIC);
+    }
+}
+
+pub(crate) fn take_reset_record() -> Option<(u16, u32)> {
+    unsafe {
+        enable_backup_domain();
+        if read(BKP_DR1) & 0xFFFF != RESET_RECORD_MAGIC {
+            return None;
+        }
+        write(BKP_DR1, 0);
+        let value = (read(BKP_DR4) & 0xFFFF) << 16 | read(BKP_DR3) & 0xFFFF;
+        Some((read(BKP_DR2) as u16, value))
+    }
+}
+
+pub(crate) fn system_reset() -> ! {
+    cortex_m::peripheral::SCB::sys_reset()
+}
+
+/// Enables the clocks of the power control and the backup domain and
+/// allows writes to the backup domain (RTC and backup registers)
+unsafe fn enable_backup_domain() {
+    modify(RCC_APB1ENR, |r| r | RCC_APB1ENR_PWREN | RCC_APB1ENR_BKPEN);
+    modify(PWR_CR, |r| r | PWR_CR_DBP);
+}
+
+/// Enters STOP mode until an EXTI line fires, e.g. the RTC alarm at the
+/// next timer deadline or a GPIO interrupt.
+fn stop(pending: fn() -> bool) {
+    let mut scb = unsafe { cortex_m::Peripherals::steal() }.SCB;
+    cortex_m::interrupt::disable();
+    if pending() {
+        unsafe { cortex_m::interrupt::enable() };
+        return;
+    }
+    unsafe {
+        init_rtc();
+        let start = rtc_counter();
+        let deadline = alarm();
+        if deadline != u64::MAX {
+            let ticks = deadline.saturating_sub(now()).min(u32::MAX as u64) as u32;
+            set_rtc_alarm(start.wrapping_add(ticks));
+        }
+        // STOP mode runs on the HSI afterwards
+        let clock_control = read(RCC_CR);
+        let clock_config = read(RCC_CFGR);
+        modify(PWR_CR, |r| (r & !PWR_CR_PDDS) | PWR_CR_LPDS);
+        scb.set_sleepdeep();
+        cortex_m::asm::wfi();
+        scb.clear_sleepdeep();
+        restore_clocks(clock_control, clock_config);
+        advance_clock(rtc_counter().wrapping_sub(start) as u64);
+        cortex_m::interrupt::enable();
+    }
+}
+
+/// Starts the RTC with the LSI as clock source and enables the alarm
+/// interrupt (EXTI line 17) that wakes the core from STOP mode.
+/// An RTC that was already enabled (e.g. in the backup domain before a
+/// reset) keeps its clock source.
+unsafe fn init_rtc() {
+    if RTC_READY {
+        return;
+    }
+    enable_backup_domain();
+    modify(RCC_CSR, |r| r | RCC_CSR_LSION);
+    while read(RCC_CSR) & RCC_CSR_LSIRDY == 0 {}
+    if read(RCC_BDCR) & RCC_BDCR_RTCEN == 0 {
+        modify(RCC_BDCR, |r| r | RCC_BDCR_RTCSEL_LSI | RCC_BDCR_RTCEN);
+    }
+    // wait until the APB registers are synchronized with the RTC
+    modify(RTC_CRL, |r| r & !RTC_CRL_RSF);
+    while read(RTC_CRL) & RTC_CRL_RSF == 0 {}
+    configure_rtc(|| {
+        let prescaler = LSI_HERTZ / TICKS_PER_SECOND - 1;
+        write(RTC_PRLH, prescaler >> 16);
+        write(RTC_PRLL, prescaler & 0xFFFF);
+    });
+    write(RTC_CRH, RTC_CRH_ALRIE);
+    modify(EXTI_RTSR, |r| r | EXTI_RTC_ALARM);
+    modify(EXTI_IMR, |r| r | EXTI_RTC_ALARM);
+    NVIC::unmask(Interrupt::RTCALARM);
+    RTC_READY = true;
+}
+
+/// RTC registers can only be written in configuration mode,
+/// the last write has to finish before the next configuration.
+unsafe fn configure_rtc(configure: impl FnOnce()) {
+    while read(RTC_CRL) & RTC_CRL_RTOFF == 0 {}
+    modify(RTC_CRL, |r| r | RTC_CRL_CNF);
+    configure();
+    modify(RTC_CRL, |r| r & !RTC_CRL_CNF);
+    while read(RTC_CRL) & RTC_CRL_RTOFF == 0 {}
+}
+
+unsafe fn set_rtc_alarm(counter: u32) {
+    configure_rtc(|| {
+        write(RTC_ALRH, counter >> 16);
+        write(RTC_ALRL, counter & 0xFFFF);
+    })
+}
+
+/// The counter is split into two registers,
+/// the high half is read again in case the low half overflowed in between.
+unsafe fn rtc_counter() -> u32 {
+    loop {
+        let high = read(RTC_CNTH);
+        let low = read(RTC_CNTL);
+        if read(RTC_CNTH) == high {
+            return (high << 16) | (low & 0xFFFF);
+        }
+    }
+}
+
+/// STOP mode turns off the HSE and the PLL and switches to the HSI
+unsafe fn restore_clocks(clock_control: u32, clock_config: u32) {
+    if clock_control & RCC_CR_HSEON != 0 {
+        modify(RCC_CR, |r| r | RCC_CR_HSEON);
+        while read(RCC_CR) & RCC_CR_HSERDY == 0 {}
+    }
+    if clock_control & RCC_CR_PLLON != 0 {
+        modify(RCC_CR, |r| r | RCC_CR_PLLON);
+        while read(RCC_CR) & RCC_CR_PLLRDY == 0 {}
+    }
+    let source = clock_config & RCC_CFGR_SW;
+    modify(RCC_CFGR, |r| (r & !RCC_CFGR_SW) | source);
+    // SWS (bits 3:2) reports the active source
+    while (read(RCC_CFGR) >> 2) & RCC_CFGR_SW != source {}
+}
+
+#[inline]
+pub(super) unsafe fn read(address: usize) -> u32 {
+    read_volatile(address as *const u32)
+}
+
+#[inline]
+pub(super) unsafe fn write(address: usize, value: u32) {
+    write_volatile(address as *mut u32, value)
+}
+
+#[inline]
+pub(super) unsafe fn modify(address: usize, f: impl FnOnce(u32) -> u32) {
+    write(address, f(read(address)))
+}
+
+/// Only wakes the core, the clock is advanced by ``stop``
+#[interrupt]
+fn RTCALARM() {
+    unsafe {
+        modify(RTC_CRL, |r| r & !RTC_CRL_ALRF);
+        write(EXTI_PR, EXTI_RTC_ALARM);
+    }
+}
@@ -0,0 +1,61 @@
+//! The independent watchdog (IWDG), see the reference manual RM0008 section 19.
+//! It is clocked by the LSI (nominal 40 kHz) and keeps running in STOP mode.
+use super::power::{modify, read, write, RCC_CSR};
+use core::time::Duration;
+
+const IWDG_KR: usize = 0x4000_3000;
+const IWDG_PR: usize = 0x4000_3004;
+const IWDG_RLR: usize = 0x4000_3008;
+const IWDG_SR: usize = 0x4000_300C;
+const DBGMCU_CR: usize = 0xE004_2004;
+
+const KEY_START: u32 = 0xCCCC;
+const KEY_UNLOCK: u32 = 0x5555;
+const KEY_FEED: u32 = 0xAAAA;
+const MAX_RELOAD: u32 = 0x0FFF;
+/// Stops the watchdog while a debugger halts the core
+const DBGMCU_CR_DBG_IWDG_STOP: u32 = 1 << 8;
+const RCC_CSR_RMVF: u32 = 1 << 24;
+const RCC_CSR_IWDGRSTF: u32 = 1 << 29;
+
+const LSI_HERTZ: u64 = 40_000;
+
+/// Timeouts above 26 seconds (the longest one) are shortened
+pub(crate) fn start_watchdog(timeout: Duration) {
+    let lsi_ticks = timeout.as_millis() as u64 * LSI_HERTZ / 1000;
+    // the prescaler divides by 4 << prescaler, at most by 256
+    let mut prescaler = 0;
+    while prescaler < 6 && lsi_ticks / (4 << prescaler) > MAX_RELOAD as u64 + 1 {
+        prescaler += 1;
+    }
+    let reload = (lsi_ticks / (4 << prescaler))
+        .max(1)
+        .min(MAX_RELOAD as u64 + 1) as u32
+        - 1;
+    unsafe {
+        modify(DBGMCU_CR, |r| r | DBGMCU_CR_DBG_IWDG_STOP);
+        // starting the watchdog also starts the LSI
+        write(IWDG_KR, KEY_START);
+        write(IWDG_KR, KEY_UNLOCK);
+        write(IWDG_PR, prescaler);
+        write(IWDG_RLR, reload);
+        // wait until the prescaler and the reload value are updated
+        while read(IWDG_SR) != 0 {}
+        write(IWDG_KR, KEY_FEED);
+    }
+}
+
+#[inline]
+pub(crate) fn feed_watchdog() {
+    unsafe { write(IWDG_KR, KEY_FEED) }
+}
+
+/// True if the last reset was caused by the watchdog.
+/// Clears the reset flags.
+pub(crate) fn take_watchdog_reset() -> bool {
+    unsafe {
+        let watchdog = read(RCC_CSR) & RCC_CSR_IWDGRSTF != 0;
+        modify(RCC_CSR, |r| r | RCC_CSR_RMVF);
+        watchdog
+    }
+}
@@ -1,42 +1,379 @@
+use crate::device::interrupt::{self, CriticalSection};
 use crate::device::ExtiEvent;
-use cortex_m::interrupt::CriticalSection;
-use heapless::consts::*;
-use heapless::spsc::{Queue, SingleCore};
-
-// TODO: multicore with feature
-#[inline]
-pub(crate) fn get_queue() -> &'static mut Queue<Event, U32, u8, SingleCore> {
-    // TODO: prevent heap allocations in interrupts!
-    // TODO: make it a stream? from futures-util
-    // static mut EVENT_QUEUE: Lazy<VecDeque<Event>> = Lazy::new(|| VecDeque::with_capacity(10));
-    // unsafe { EVENT_QUEUE.deref_mut() }
-    static mut EVENT_QUEUE: Option<Queue<Event, U32, u8, SingleCore>> = None;
+use crate::resources::{gpio::TriggerEdge, ResourceError};
+use crate::time::{self, Timestamp};
+use core::time::Duration;
+
+/// Capacity of the event queue unless [configure] provides another buffer
+pub const DEFAULT_CAPACITY: usize = 32;
+
+/// What happens to an event that does not fit into the full event queue.
+/// Lost events are counted in ``sys:sys/lost_events``.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The new event is dropped
+    DropNewest,
+    /// The oldest queued event is dropped to make room for the new one
+    DropOldest,
+    /// An event that is already queued with the same level is not queued
+    /// again, even if the queue has room. New events that differ from all queued ones are
+    /// dropped if the queue is full.
+    Coalesce,
+}
+
+impl Default for OverflowPolicy {
+    fn default() -> Self {
+        OverflowPolicy::DropNewest
+    }
+}
+
+/// An event and the time it was pushed
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimedEvent {
+    pub event: Event,
+    pub timestamp: Timestamp,
+    /// Level of the pin after a gpio edge (high for rising edges), sampled
+    /// by the interrupt handler together with the timestamp.
+    /// False for other events.
+    pub high: bool,
+}
+
+/// An empty slot of an event buffer
+pub const NO_EVENT: Option<TimedEvent> = None;
+
+/// Ring buffer of pushed events, it is only accessed in critical sections
+struct EventQueue {
+    buffer: &'static mut [Option<TimedEvent>],
+    /// Index of the oldest event
+    head: usize,
+    len: usize,
+    /// ``Event::Timer`` is kept apart from the queue entries, so it is never
+    /// lost: a lost timer event would keep sleeping tasks from waking up.
+    /// Only the first unhandled timer event is kept.
+    timer: Option<Timestamp>,
+    policy: OverflowPolicy,
+    /// Maximum length the queue reached
+    high_water: usize,
+    /// Events dropped because the queue was full or coalesced
+    lost: u32,
+}
+
+impl EventQueue {
+    fn new(buffer: &'static mut [Option<TimedEvent>], policy: OverflowPolicy) -> Self {
+        EventQueue {
+            buffer,
+            head: 0,
+            len: 0,
+            timer: None,
+            policy,
+            high_water: 0,
+            lost: 0,
+        }
+    }
+    fn push(&mut self, event: TimedEvent) {
+        if event.event == Event::Timer {
+            self.timer = self.timer.or(Some(event.timestamp));
+            return;
+        }
+        if self.policy == OverflowPolicy::Coalesce
+            && self
+                .iter()
+                .any(|queued| queued.event == event.event && queued.high == event.high)
+        {
+            self.lost = self.lost.wrapping_add(1);
+            return;
+        }
+        if self.len == self.buffer.len() {
+            self.lost = self.lost.wrapping_add(1);
+            match self.policy {
+                OverflowPolicy::DropOldest => {
+                    self.pop_queued();
+                }
+                _ => return,
+            }
+        }
+        let tail = (self.head + self.len) % self.buffer.len();
+        self.buffer[tail] = Some(event);
+        self.len += 1;
+        self.high_water = self.high_water.max(self.len);
+    }
+    /// Timer events first, the executor wakes all expired timers at once
+    fn pop(&mut self) -> Option<TimedEvent> {
+        if let Some(timestamp) = self.timer.take() {
+            return Some(TimedEvent {
+                event: Event::Timer,
+                timestamp,
+                high: false,
+            });
+        }
+        self.pop_queued()
+    }
+    fn pop_queued(&mut self) -> Option<TimedEvent> {
+        if self.len == 0 {
+            return None;
+        }
+        let event = self.buffer[self.head].take();
+        self.head = (self.head + 1) % self.buffer.len();
+        self.len -= 1;
+        event
+    }
+    fn iter(&self) -> impl Iterator<Item = &TimedEvent> {
+        let capacity = self.buffer.len();
+        (0..self.len).filter_map(move |i| self.buffer[(self.head + i) % capacity].as_ref())
+    }
+    /// Also false if only the timer event is pending
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len == 0 && self.timer.is_none()
+    }
+}
+
+static mut QUEUE: Option<EventQueue> = None;
+
+/// Has to be called inside a critical section
+fn queue(_cs: &CriticalSection) -> &'static mut EventQueue {
+    static mut DEFAULT_BUFFER: [Option<TimedEvent>; DEFAULT_CAPACITY] =
+        [NO_EVENT; DEFAULT_CAPACITY];
     unsafe {
-        if let None = EVENT_QUEUE {
-            EVENT_QUEUE = Some(Queue::u8_sc());
+        if QUEUE.is_none() {
+            QUEUE = Some(EventQueue::new(
+                &mut DEFAULT_BUFFER,
+                OverflowPolicy::default(),
+            ));
         }
-        EVENT_QUEUE.as_mut().unwrap()
+        QUEUE.as_mut().unwrap()
     }
 }
 
+/// Replaces the event queue with one that stores its events in ``buffer``,
+/// so its capacity is the length of the buffer. Queued events are moved to
+/// the new queue as far as they fit.
+/// Usually called by the generated ``init`` if the ``events`` entry of the
+/// sys section is configured:
+/// ```ignore
+/// "sys": { "events": { "capacity": 64, "overflow": "drop_oldest" } }
+/// ```
+pub fn configure(buffer: &'static mut [Option<TimedEvent>], policy: OverflowPolicy) {
+    assert!(!buffer.is_empty(), "event buffer without capacity");
+    interrupt::free(move |cs| {
+        let old = queue(cs);
+        let mut new = EventQueue::new(buffer, policy);
+        new.lost = old.lost;
+        while let Some(event) = old.pop() {
+            new.push(event)
+        }
+        new.high_water = new.len;
+        unsafe { QUEUE = Some(new) }
+    })
+}
+
+pub fn set_overflow_policy(policy: OverflowPolicy) {
+    interrupt::free(|cs| queue(cs).policy = policy)
+}
+
 #[non_exhaustive]
 #[derive(Clone, Ord, PartialOrd, Eq, PartialEq)]
 pub enum Event {
     DeviceInterrupt,
     ExternalInterrupt(ExtiEvent),
+    /// The monotonic clock reached the earliest timer deadline
+    Timer,
+    /// A [UserEvent](crate::resources::UserEvent) was raised, it holds its index
+    User(u8),
 }
 
-//TODO: add critical section?
 #[inline]
-pub fn next() -> Option<Event> {
+pub fn next() -> Option<TimedEvent> {
     // log::trace!("get next event");
-    get_queue().dequeue()
+    interrupt::free(|cs| queue(cs).pop())
 }
 
+/// Queues the event with the current time, so interrupt handlers should
+/// push their events first. A full queue drops an event as selected by its
+/// [OverflowPolicy].
 #[inline]
-pub fn push(event: Event, _cs: &CriticalSection) {
+pub fn push(event: Event, cs: &CriticalSection) {
+    push_level(event, false, cs)
+}
+
+/// Queues a gpio edge with the level of the pin after the edge, see [push].
+/// The level has to be read in the interrupt handler: the pin may have
+/// changed again by the time the executor handles the event.
+#[inline]
+pub fn push_level(event: Event, high: bool, cs: &CriticalSection) {
     // log::trace!("push event {:?}", event);
-    get_queue().enqueue(event).expect("filled event_queue")
+    #[cfg(feature = "trace")]
+    crate::trace::record(
+        crate::trace::Kind::EventPush,
+        crate::trace::NO_EXECUTOR as usize,
+        crate::trace::event_code(&event),
+    );
+    if event == Event::Timer {
+        crate::executor::timer_fired(cs)
+    }
+    queue(cs).push(TimedEvent {
+        event,
+        timestamp: Timestamp::now(),
+        high,
+    });
+    crate::device::wake_thread();
+}
+
+/// Drops all queued events and restores the default queue
+#[cfg(feature = "host")]
+pub(crate) fn reset() {
+    interrupt::free(|_| unsafe { QUEUE = None })
+}
+
+/// Queued events, without the timer event that is kept apart,
+/// so it does not exceed the [capacity]
+#[inline]
+pub(crate) fn len() -> usize {
+    interrupt::free(|cs| queue(cs).len)
+}
+
+/// True if neither events nor the timer event are pending
+#[inline]
+pub(crate) fn is_empty() -> bool {
+    interrupt::free(|cs| queue(cs).is_empty())
+}
+
+#[inline]
+pub(crate) fn capacity() -> usize {
+    interrupt::free(|cs| queue(cs).buffer.len())
+}
+
+#[inline]
+pub(crate) fn high_water() -> usize {
+    interrupt::free(|cs| queue(cs).high_water)
+}
+
+/// Events dropped so far
+#[inline]
+pub(crate) fn lost() -> u32 {
+    interrupt::free(|cs| queue(cs).lost)
+}
+
+/// Number of events an [EventLog] keeps for subscribers that fall behind
+const LOG_LEN: usize = 8;
+
+/// The recent events of a resource, so every subscriber sees every event.
+/// Resources record their events in it, each reading [ResourceID](crate::resources::ResourceID)
+/// keeps its own [EventCursor].
+pub(crate) struct EventLog {
+    /// Events recorded so far, wraps around
+    count: u32,
+    /// Timestamp and the level after the event (high for rising edges)
+    entries: [(Timestamp, bool); LOG_LEN],
+}
+
+impl EventLog {
+    pub(crate) const fn new() -> Self {
+        EventLog {
+            count: 0,
+            entries: [(Timestamp::from_micros(0), false); LOG_LEN],
+        }
+    }
+    pub(crate) fn record(&mut self, timestamp: Timestamp, high: bool) {
+        self.entries[self.count as usize % LOG_LEN] = (timestamp, high);
+        self.count = self.count.wrapping_add(1);
+    }
+    /// The timestamp and level of the next event the subscriber did not see
+    /// yet and that passes its [EventFilter]. The first call subscribes, so
+    /// only later events are returned. A subscriber more than [LOG_LEN]
+    /// events behind skips to the oldest kept event.
+    pub(crate) fn next(&self, cursor: &mut EventCursor) -> Option<(Timestamp, bool)> {
+        let mut seen = *cursor.seen.get_or_insert(self.count);
+        loop {
+            let pending = self.count.wrapping_sub(seen);
+            if pending == 0 {
+                cursor.seen = Some(seen);
+                return None;
+            }
+            if pending > LOG_LEN as u32 {
+                seen = self.count.wrapping_sub(LOG_LEN as u32);
+            }
+            let (timestamp, high) = self.entries[seen as usize % LOG_LEN];
+            seen = seen.wrapping_add(1);
+            if cursor.filter.accepts(timestamp, high, cursor.last) {
+                cursor.seen = Some(seen);
+                cursor.last = Some(timestamp);
+                return Some((timestamp, high));
+            }
+        }
+    }
+}
+
+/// The position of one subscriber in the [EventLog] of a resource
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EventCursor {
+    /// Number of events seen, ``None`` until the first read
+    seen: Option<u32>,
+    /// Timestamp of the last event the subscriber read
+    last: Option<Timestamp>,
+    pub(crate) filter: EventFilter,
+}
+
+/// Per subscriber filter, set by the query of an event uri:
+/// ``event:gpio/pa0?debounce=20ms&edge=falling``
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct EventFilter {
+    /// Events closer than this to the last read event are skipped
+    pub debounce: Duration,
+    pub edge: TriggerEdge,
+}
+
+impl Default for EventFilter {
+    fn default() -> Self {
+        EventFilter {
+            debounce: Duration::from_micros(0),
+            edge: TriggerEdge::All,
+        }
+    }
+}
+
+impl EventFilter {
+    /// Sets ``debounce=<n>us|ms|s`` or ``edge=rising|falling|all``,
+    /// ``Ok(false)`` if the key is no filter option
+    pub(crate) fn set(&mut self, key: &str, value: &str) -> Result<bool, ResourceError> {
+        match key {
+            "debounce" => {
+                self.debounce = time::parse_duration(value).ok_or(ResourceError::ParseError)?
+            }
+            "edge" => {
+                self.edge = match value {
+                    "rising" => TriggerEdge::Rising,
+                    "falling" => TriggerEdge::Falling,
+                    "all" | "any" => TriggerEdge::All,
+                    _ => return Err(ResourceError::ParseError),
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+    fn accepts(&self, timestamp: Timestamp, high: bool, last: Option<Timestamp>) -> bool {
+        let edge = match self.edge {
+            TriggerEdge::Rising => high,
+            TriggerEdge::Falling => !high,
+            TriggerEdge::All => true,
+        };
+        edge && last.map_or(true, |last| timestamp.duration_since(last) >= self.debounce)
+    }
+}
+
+/// Size of an event with timestamp
+pub(crate) const EVENT_SIZE: usize = 9;
+
+/// Writes an event for a reader: the event byte, followed by the timestamp
+/// in microseconds if the buffer has at least [EVENT_SIZE] bytes
+pub(crate) fn write_event(value: u8, timestamp: Timestamp, buf: &mut [u8]) -> usize {
+    buf[0] = value;
+    if buf.len() < EVENT_SIZE {
+        return 1;
+    }
+    buf[1..EVENT_SIZE].copy_from_slice(&to_target_endianess!(timestamp.as_micros()));
+    EVENT_SIZE
 }
 
 impl core::fmt::Debug for Event {
@@ -44,6 +381,184 @@ impl core::fmt::Debug for Event {
         match self {
             Event::DeviceInterrupt => write!(f, "DeviceInterrupt"),
             Event::ExternalInterrupt(i) => write!(f, "ExternalInterrupt({:?})", i),
+            Event::Timer => write!(f, "Timer"),
+            Event::User(index) => write!(f, "User({})", index),
         }
     }
 }
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use super::*;
+
+    fn user(index: u8, micros: u64) -> TimedEvent {
+        TimedEvent {
+            event: Event::User(index),
+            timestamp: Timestamp::from_micros(micros),
+            high: false,
+        }
+    }
+
+    fn timer(micros: u64) -> TimedEvent {
+        TimedEvent {
+            event: Event::Timer,
+            timestamp: Timestamp::from_micros(micros),
+            high: false,
+        }
+    }
+
+    fn queue(capacity: usize, policy: OverflowPolicy) -> EventQueue {
+        EventQueue::new(vec![NO_EVENT; capacity].leak(), policy)
+    }
+
+    #[test]
+    fn the_timer_event_is_kept_apart() {
+        let mut queue = queue(2, OverflowPolicy::DropNewest);
+        queue.push(user(0, 1));
+        queue.push(timer(2));
+        queue.push(user(1, 3));
+        queue.push(timer(4));
+        assert_eq!(queue.len, 2);
+        assert_eq!(queue.high_water, 2);
+        assert_eq!(queue.lost, 0);
+        // the first timer event comes first
+        assert_eq!(queue.pop(), Some(timer(2)));
+        assert_eq!(queue.pop(), Some(user(0, 1)));
+        assert_eq!(queue.pop(), Some(user(1, 3)));
+        assert!(queue.is_empty());
+        queue.push(timer(5));
+        assert_eq!(queue.len, 0);
+        assert!(!queue.is_empty());
+    }
+
+    #[test]
+    fn drop_newest_keeps_the_queued_events() {
+        let mut queue = queue(2, OverflowPolicy::DropNewest);
+        for index in 0..4 {
+            queue.push(user(index, index as u64));
+        }
+        assert_eq!(queue.lost, 2);
+        assert_eq!(queue.pop(), Some(user(0, 0)));
+        assert_eq!(queue.pop(), Some(user(1, 1)));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn drop_oldest_keeps_the_recent_events() {
+        let mut queue = queue(2, OverflowPolicy::DropOldest);
+        for index in 0..4 {
+            queue.push(user(index, index as u64));
+        }
+        assert_eq!(queue.lost, 2);
+        assert_eq!(queue.high_water, 2);
+        assert_eq!(queue.pop(), Some(user(2, 2)));
+        assert_eq!(queue.pop(), Some(user(3, 3)));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn coalesce_queues_an_event_once() {
+        let mut queue = queue(2, OverflowPolicy::Coalesce);
+        queue.push(user(0, 1));
+        queue.push(user(0, 2));
+        assert_eq!(queue.len, 1);
+        queue.push(user(1, 3));
+        // full, and it differs from the queued events
+        queue.push(user(2, 4));
+        assert_eq!(queue.lost, 2);
+        assert_eq!(queue.pop(), Some(user(0, 1)));
+        // queued again once the first one is handled
+        queue.push(user(0, 5));
+        assert_eq!(queue.pop(), Some(user(1, 3)));
+        assert_eq!(queue.pop(), Some(user(0, 5)));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn coalesce_keeps_both_edges_of_a_pin() {
+        let pin = crate::resources::gpio::Pin::from_str("pa0").unwrap();
+        let edge = |micros, high| TimedEvent {
+            event: Event::ExternalInterrupt(ExtiEvent::Gpio(pin)),
+            timestamp: Timestamp::from_micros(micros),
+            high,
+        };
+        let mut queue = queue(4, OverflowPolicy::Coalesce);
+        queue.push(edge(1, true));
+        queue.push(edge(2, false));
+        queue.push(edge(3, true));
+        assert_eq!(queue.lost, 1);
+        assert_eq!(queue.pop(), Some(edge(1, true)));
+        assert_eq!(queue.pop(), Some(edge(2, false)));
+        assert_eq!(queue.pop(), None);
+    }
+
+    fn record(log: &mut EventLog, millis: u64, high: bool) {
+        log.record(Timestamp::from_micros(millis * 1000), high)
+    }
+
+    fn next(log: &EventLog, cursor: &mut EventCursor) -> Option<u64> {
+        log.next(cursor)
+            .map(|(timestamp, _high)| timestamp.as_micros() / 1000)
+    }
+
+    #[test]
+    fn cursors_see_the_events_after_they_subscribed() {
+        let mut log = EventLog::new();
+        let mut early = EventCursor::default();
+        record(&mut log, 1, true);
+        assert_eq!(next(&log, &mut early), None);
+        record(&mut log, 2, false);
+        let mut late = EventCursor::default();
+        assert_eq!(next(&log, &mut late), None);
+        record(&mut log, 3, true);
+        assert_eq!(next(&log, &mut early), Some(2));
+        assert_eq!(next(&log, &mut early), Some(3));
+        assert_eq!(next(&log, &mut early), None);
+        assert_eq!(next(&log, &mut late), Some(3));
+        assert_eq!(next(&log, &mut late), None);
+    }
+
+    #[test]
+    fn cursors_that_fall_behind_skip_to_the_oldest_kept_event() {
+        let mut log = EventLog::new();
+        let mut cursor = EventCursor::default();
+        assert_eq!(next(&log, &mut cursor), None);
+        for millis in 0..LOG_LEN as u64 + 3 {
+            record(&mut log, millis, true);
+        }
+        for millis in 3..LOG_LEN as u64 + 3 {
+            assert_eq!(next(&log, &mut cursor), Some(millis));
+        }
+        assert_eq!(next(&log, &mut cursor), None);
+    }
+
+    #[test]
+    fn filters_skip_edges_and_bounces() {
+        let mut filter = EventFilter::default();
+        assert!(matches!(filter.set("edge", "falling"), Ok(true)));
+        assert!(matches!(filter.set("debounce", "5ms"), Ok(true)));
+        assert!(matches!(filter.set("fmt", "text"), Ok(false)));
+        assert!(matches!(
+            filter.set("edge", "up"),
+            Err(ResourceError::ParseError)
+        ));
+        assert!(matches!(
+            filter.set("debounce", "5"),
+            Err(ResourceError::ParseError)
+        ));
+        let mut log = EventLog::new();
+        let mut cursor = EventCursor {
+            filter,
+            ..EventCursor::default()
+        };
+        assert_eq!(next(&log, &mut cursor), None);
+        record(&mut log, 10, false);
+        record(&mut log, 11, true);
+        // a bounce 2 ms after the last falling edge
+        record(&mut log, 12, false);
+        record(&mut log, 15, false);
+        assert_eq!(next(&log, &mut cursor), Some(10));
+        assert_eq!(next(&log, &mut cursor), Some(15));
+        assert_eq!(next(&log, &mut cursor), None);
+    }
+}
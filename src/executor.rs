@@ -1,85 +1,1029 @@
 use super::*;
-use crate::events::Event;
-use alloc::collections::BTreeMap;
-use alloc::vec::Vec;
-use core::task::Waker;
+use crate::device::interrupt::{self, CriticalSection, Mutex, Nr};
+use crate::events::{Event, TimedEvent};
+use crate::time::Instant;
+use core::cell::{Cell, RefCell};
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::{RawWaker, RawWakerVTable, Waker};
+use core::time::Duration;
 use device::handle_exti_event;
-use heapless::consts::*;
-use heapless::spsc::{Queue, SingleCore};
-use task::TaskID;
+use heapless::mpmc::{Q32, Q8};
+use storage::{List, TaskMap, TaskQueue};
+use task::{Priority, RawTask, Scheduling, TaskID, TaskStats, Timing, WakeReason};
+
+/// Slot of the thread-mode executor of [Runtime::run]
+pub(crate) const THREAD: usize = 0;
+/// Number of [InterruptExecutor]s that can be started
+const MAX_INTERRUPT_EXECUTORS: usize = 2;
+/// The thread-mode executor and the interrupt executors.
+/// Task wakers store the slot of their executor in two bits.
+pub(crate) const SLOTS: usize = 1 + MAX_INTERRUPT_EXECUTORS;
+
+/// Tasks spawned and woken for the executor in the same slot.
+/// The executor only touches its own state while it runs, everyone else
+/// (its own tasks, other executors, interrupt handlers) goes through these
+/// lock-free queues.
+struct Queues {
+    /// Tasks spawned while the executor runs or from interrupt handlers
+    spawn: Q8<SpawnedTask>,
+    /// Tasks woken by their wakers
+    wake: Q32<(TaskID, WakeReason)>,
+    /// Set if a wake did not fit into the wake queue.
+    /// All tasks are woken then, futures have to cope with spurious wakes anyway.
+    wake_overflow: AtomicBool,
+    /// Set if a task was queued for spawning or waking and not drained yet
+    pending: AtomicBool,
+}
+
+const NEW_QUEUES: Queues = Queues {
+    spawn: Q8::new(),
+    wake: Q32::new(),
+    wake_overflow: AtomicBool::new(false),
+    pending: AtomicBool::new(false),
+};
+static QUEUES: [Queues; SLOTS] = [NEW_QUEUES; SLOTS];
+const NO_TIMERS: Option<List<(Instant, Waker)>> = None;
+/// Timers registered by the task that every executor polls right now,
+/// at most one per waker (see [insert_timer]). The executor takes them over
+/// after the poll, so they are only bounded by its own timer list.
+/// A list is created by the first registration of its executor.
+static REGISTERED_TIMERS: Mutex<RefCell<[Option<List<(Instant, Waker)>>; SLOTS]>> =
+    Mutex::new(RefCell::new([NO_TIMERS; SLOTS]));
+/// Event wakers of the tasks of all executors, at most one per event and
+/// waker (see [insert_waker]). Events are dispatched by the thread-mode
+/// executor, it takes them over. The list is created by the first
+/// registration.
+static REGISTERED_WAKERS: Mutex<RefCell<Option<List<(Event, Waker)>>>> =
+    Mutex::new(RefCell::new(None));
+/// Set while the thread-mode executor polls tasks
+static RUNNING: AtomicBool = AtomicBool::new(false);
+/// Slot of the executor that runs right now.
+/// Interrupt executors restore the slot they preempted when they return.
+static CURRENT: AtomicUsize = AtomicUsize::new(THREAD);
+/// Reason recorded for wakes from thread mode.
+/// It is set while the executor wakes event and timer wakers.
+static WAKE_REASON: Mutex<RefCell<WakeReason>> = Mutex::new(RefCell::new(WakeReason::Waker));
+/// Earliest timer deadline of every executor, the device alarm is set to
+/// the earliest of them
+static TIMER_DEADLINES: Mutex<RefCell<[u64; SLOTS]>> = Mutex::new(RefCell::new([u64::MAX; SLOTS]));
+/// Timing of the task that every executor polls right now, it is moved
+/// back to the task after the poll
+static CURRENT_TIMING: Mutex<Cell<[Option<Timing>; SLOTS]>> = Mutex::new(Cell::new([None; SLOTS]));
+/// Owner (the address of its [InterruptExecutor]) and interrupt number of
+/// the started interrupt executors, slot one is the first entry
+static STARTED: Mutex<Cell<[Option<(usize, u8)>; MAX_INTERRUPT_EXECUTORS]>> =
+    Mutex::new(Cell::new([None; MAX_INTERRUPT_EXECUTORS]));
+/// Only accessed by the interrupt handler of the executor, once it is started
+static mut INTERRUPT_EXECUTORS: [Option<Executor>; MAX_INTERRUPT_EXECUTORS] = [None, None];
+
+/// A task moved through the spawn queue.
+/// Tasks are not Send because they are only polled by their executor.
+/// Handing them over once on a single core is fine.
+struct SpawnedTask(RawTask);
+unsafe impl Send for SpawnedTask {}
+
+/// Tasks listed by the [Snapshot]
+const SNAPSHOT_TASKS: usize = 32;
+
+/// Statistics of the thread-mode executor for the ``sys:`` resources.
+/// Resources are read by tasks, i.e. while the executor is borrowed by
+/// [Executor::run], so they read this copy instead. The executor refreshes
+/// the counters before every poll and the tasks after every pass.
+struct Snapshot {
+    /// The first [SNAPSHOT_TASKS] tasks by id
+    tasks: [Option<TaskSnapshot>; SNAPSHOT_TASKS],
+    counters: Counters,
+}
+
+/// A task in the [Snapshot]
+#[derive(Clone)]
+pub(crate) struct TaskSnapshot {
+    pub(crate) id: TaskID,
+    pub(crate) priority: Priority,
+    pub(crate) stats: TaskStats,
+    pub(crate) timing: Option<Timing>,
+}
+
+/// Fill level of the task queue and deadline misses, see [Snapshot]
+#[derive(Clone, Copy)]
+pub(crate) struct Counters {
+    pub(crate) queue_len: usize,
+    /// ``None`` if the queue is unbounded
+    pub(crate) queue_capacity: Option<usize>,
+    pub(crate) queue_high_water: usize,
+    /// Deadline misses of all tasks, including the removed ones
+    pub(crate) deadline_misses: u32,
+}
+
+const NO_TASK: Option<TaskSnapshot> = None;
+const EMPTY_SNAPSHOT: Snapshot = Snapshot {
+    tasks: [NO_TASK; SNAPSHOT_TASKS],
+    counters: Counters {
+        queue_len: 0,
+        queue_capacity: None,
+        queue_high_water: 0,
+        deadline_misses: 0,
+    },
+};
+static SNAPSHOT: Mutex<RefCell<Snapshot>> = Mutex::new(RefCell::new(EMPTY_SNAPSHOT));
+
+/// The task at ``index`` of the snapshot, ``None`` after the last one
+pub(crate) fn task_snapshot(index: usize) -> Option<TaskSnapshot> {
+    interrupt::free(|cs| SNAPSHOT.borrow(cs).borrow().tasks.get(index)?.clone())
+}
+
+/// The counters of the snapshot
+pub(crate) fn counters() -> Counters {
+    interrupt::free(|cs| SNAPSHOT.borrow(cs).borrow().counters)
+}
+
+/// Hands the task to the executor in ``slot``.
+/// Before the thread-mode executor runs (i.e. during initialization) the
+/// task is inserted directly, the executor is not borrowed by
+/// [Executor::run] then. Otherwise it goes through the spawn queue.
+pub(crate) fn spawn(task: RawTask, slot: usize) -> Result<(), RuntimeError> {
+    if slot == THREAD && !RUNNING.load(Ordering::Acquire) && !device::in_interrupt() {
+        Runtime::get().executor.insert(task);
+        return Ok(());
+    }
+    if slot != THREAD && interrupt_number(slot).is_none() {
+        return Err(RuntimeError::UninitializedAccess);
+    }
+    QUEUES[slot]
+        .spawn
+        .enqueue(SpawnedTask(task))
+        .map_err(|_| RuntimeError::TaskQueueIsFull)?;
+    notify(slot);
+    Ok(())
+}
+
+/// Called by task wakers, safe to use from tasks and interrupt handlers
+pub(crate) fn wake(task_id: TaskID, slot: usize) {
+    let reason = if device::in_interrupt() {
+        WakeReason::Interrupt
+    } else {
+        interrupt::free(|cs| WAKE_REASON.borrow(cs).borrow().clone())
+    };
+    if QUEUES[slot].wake.enqueue((task_id, reason)).is_err() {
+        QUEUES[slot].wake_overflow.store(true, Ordering::Release)
+    }
+    notify(slot);
+}
+
+/// Lets the executor in ``slot`` know that it has work queued:
+/// interrupt executors are pended, the thread-mode executor resumes after
+/// the current interrupt handler
+fn notify(slot: usize) {
+    QUEUES[slot].pending.store(true, Ordering::Release);
+    match slot {
+        THREAD => {
+            if device::in_interrupt() {
+                device::wake_thread()
+            }
+        }
+        slot => {
+            if let Some(interrupt) = interrupt_number(slot) {
+                device::pend_interrupt(interrupt)
+            }
+        }
+    }
+}
+
+/// True if the thread-mode executor has work left, i.e. queued events,
+/// spawns or wakes.
+/// The device checks it with interrupts masked right before it sleeps.
+pub(crate) fn pending() -> bool {
+    QUEUES[THREAD].pending.load(Ordering::Acquire) || !events::is_empty()
+}
+
+/// Pends the interrupt executors whose earliest timer expired.
+/// Called when the device pushes an ``Event::Timer``.
+pub(crate) fn timer_fired(cs: &CriticalSection) {
+    let now = device::now();
+    let deadlines = *TIMER_DEADLINES.borrow(cs).borrow();
+    for slot in 1..SLOTS {
+        if deadlines[slot] <= now {
+            notify(slot)
+        }
+    }
+}
+
+/// Registers the timer at the executor of the current task.
+/// The executor runs the poll of the task, it takes the timer over after it.
+pub(crate) fn register_timer(deadline: Instant, waker: &Waker) {
+    let slot = CURRENT.load(Ordering::Acquire);
+    interrupt::free(|cs| {
+        let mut registered = REGISTERED_TIMERS.borrow(cs).borrow_mut();
+        insert_timer(
+            registered[slot].get_or_insert_with(List::new),
+            deadline,
+            waker.clone(),
+        )
+    });
+    notify_running(slot);
+}
+
+/// Ends the job of the task that is polled right now.
+/// Returns the release of its next job, ``None`` if the task has no deadline.
+/// The executor counts the miss when it takes the timing back.
+pub(crate) fn complete_job() -> Option<Instant> {
+    let slot = CURRENT.load(Ordering::Acquire);
+    let now = Instant::now();
+    interrupt::free(|cs| {
+        let current = CURRENT_TIMING.borrow(cs);
+        let mut timings = current.get();
+        let (release, _missed) = timings[slot].as_mut()?.complete_job(now);
+        current.set(timings);
+        Some(release)
+    })
+}
+
+/// Registers the waker at the thread-mode executor, it dispatches the events
+pub(crate) fn register_waker(trigger: &Event, waker: &Waker) {
+    #[cfg(feature = "trace")]
+    crate::trace::record(
+        crate::trace::Kind::WakerRegistered,
+        CURRENT.load(Ordering::Acquire),
+        crate::trace::event_code(trigger),
+    );
+    interrupt::free(|cs| {
+        let mut registered = REGISTERED_WAKERS.borrow(cs).borrow_mut();
+        insert_waker(registered.get_or_insert_with(List::new), trigger, waker)
+    });
+    notify_running(THREAD);
+}
+
+/// Like [notify], except that an interrupt executor is not pended again
+/// while it runs, it drains its queues after the current poll anyway
+#[inline]
+fn notify_running(slot: usize) {
+    if slot != THREAD && CURRENT.load(Ordering::Acquire) == slot {
+        QUEUES[slot].pending.store(true, Ordering::Release)
+    } else {
+        notify(slot)
+    }
+}
+
+#[inline]
+fn interrupt_number(slot: usize) -> Option<u8> {
+    let index = slot.checked_sub(1)?;
+    interrupt::free(|cs| STARTED.borrow(cs).get().get(index).cloned()?)
+        .map(|(_, interrupt)| interrupt)
+}
+
+#[inline]
+fn interrupt_executor(slot: usize) -> Option<&'static mut Executor> {
+    unsafe { INTERRUPT_EXECUTORS.get_mut(slot.checked_sub(1)?)?.as_mut() }
+}
+
+/// Drops queued spawns and wakes and the interrupt executors,
+/// the tasks belong to a dropped runtime
+#[cfg(feature = "host")]
+pub(crate) fn reset() {
+    for queues in QUEUES.iter() {
+        while queues.spawn.dequeue().is_some() {}
+        while queues.wake.dequeue().is_some() {}
+        queues.wake_overflow.store(false, Ordering::Release);
+        queues.pending.store(false, Ordering::Release);
+    }
+    RUNNING.store(false, Ordering::Release);
+    CURRENT.store(THREAD, Ordering::Release);
+    set_wake_reason(WakeReason::Waker);
+    interrupt::free(|cs| {
+        *TIMER_DEADLINES.borrow(cs).borrow_mut() = [u64::MAX; SLOTS];
+        *REGISTERED_TIMERS.borrow(cs).borrow_mut() = [NO_TIMERS; SLOTS];
+        *REGISTERED_WAKERS.borrow(cs).borrow_mut() = None;
+        CURRENT_TIMING.borrow(cs).set([None; SLOTS]);
+        *SNAPSHOT.borrow(cs).borrow_mut() = EMPTY_SNAPSHOT;
+        STARTED.borrow(cs).set([None; MAX_INTERRUPT_EXECUTORS]);
+    });
+    unsafe { INTERRUPT_EXECUTORS = [None, None] };
+}
+
+/// The host has no interrupt controller: the pended interrupt executors run
+/// between two polls of the thread-mode executor
+#[cfg(feature = "host")]
+fn run_pended() {
+    for slot in 1..SLOTS {
+        while QUEUES[slot].pending.load(Ordering::Acquire) && interrupt_number(slot).is_some() {
+            device::host::simulate_interrupt(|_| run_interrupt_executor(slot))
+        }
+    }
+}
+
+/// Runs the interrupt executor in ``slot`` from its interrupt handler
+fn run_interrupt_executor(slot: usize) {
+    if let Some(executor) = interrupt_executor(slot) {
+        let preempted = CURRENT.swap(slot, Ordering::AcqRel);
+        executor.run();
+        CURRENT.store(preempted, Ordering::Release);
+    }
+}
+
+/// Sets the timing of the task that the executor in ``slot`` polls,
+/// returns the one of the last task
+fn set_current_timing(slot: usize, timing: Option<Timing>) -> Option<Timing> {
+    interrupt::free(|cs| {
+        let current = CURRENT_TIMING.borrow(cs);
+        let mut timings = current.get();
+        let last = core::mem::replace(&mut timings[slot], timing);
+        current.set(timings);
+        last
+    })
+}
+
+/// The timer of the earliest job deadline wakes no task, it only makes the
+/// executor check the deadlines. Its own vtable keeps it apart from other
+/// timers without a task, e.g. the one of the watchdog.
+static DEADLINE_WAKER_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(clone_deadline_waker, no_wake, no_wake, no_wake);
+
+fn deadline_waker() -> Waker {
+    unsafe { Waker::from_raw(clone_deadline_waker(core::ptr::null())) }
+}
+unsafe fn clone_deadline_waker(_: *const ()) -> RawWaker {
+    RawWaker::new(core::ptr::null(), &DEADLINE_WAKER_VTABLE)
+}
+unsafe fn no_wake(_: *const ()) {}
+
+fn set_wake_reason(reason: WakeReason) {
+    interrupt::free(|cs| *WAKE_REASON.borrow(cs).borrow_mut() = reason)
+}
+
+/// An executor that polls its tasks in an interrupt handler.
+///
+/// Its tasks preempt the tasks of the thread-mode executor in
+/// [Runtime::run] and of interrupt executors with a lower NVIC priority as
+/// soon as they are woken, in the style of RTIC or embassy. Any interrupt
+/// that is not used otherwise can be used as software interrupt:
+/// ```ignore
+/// static URGENT: InterruptExecutor = InterruptExecutor::new();
+///
+/// #[interrupt]
+/// fn USART3() {
+///     URGENT.on_interrupt()
+/// }
+///
+/// URGENT.start(Interrupt::USART3, 1)?;
+/// URGENT.spawner().spawn(SendTask::new(control_loop()))?;
+/// ```
+/// Its tasks run in interrupt context, so they are [SendTask]s.
+/// Timers of its tasks wake them directly. Events of resources are
+/// dispatched by the thread-mode executor, a busy thread-mode task delays
+/// them until its poll returns. ``sys:sys/tasks`` only lists the tasks of
+/// the thread-mode executor.
+pub struct InterruptExecutor {
+    /// Zero until the executor is started
+    slot: AtomicUsize,
+}
+
+impl InterruptExecutor {
+    pub const fn new() -> Self {
+        InterruptExecutor {
+            slot: AtomicUsize::new(0),
+        }
+    }
+    /// Sets the NVIC ``priority`` of the ``interrupt`` and unmasks it.
+    /// Zero is the highest priority, every interrupt executor preempts
+    /// the thread-mode executor. Fails with ``RuntimeError::InvalidPriority``
+    /// if the NVIC does not implement the priority (16 levels on the stm32f1).
+    pub fn start<I: Nr>(&'static self, interrupt: I, priority: u8) -> Result<(), RuntimeError> {
+        if priority >= device::INTERRUPT_PRIORITIES {
+            return Err(RuntimeError::InvalidPriority);
+        }
+        let owner = self as *const Self as usize;
+        let interrupt = interrupt.nr();
+        let index = interrupt::free(|cs| {
+            let mut started = STARTED.borrow(cs).get();
+            if started.iter().flatten().any(|(other, _)| *other == owner) {
+                return Err(RuntimeError::MultipleInitializations);
+            }
+            let index = started
+                .iter()
+                .position(Option::is_none)
+                .ok_or(RuntimeError::TooManyExecutors)?;
+            started[index] = Some((owner, interrupt));
+            STARTED.borrow(cs).set(started);
+            unsafe { INTERRUPT_EXECUTORS[index] = Some(Executor::with_slot(index + 1)) };
+            Ok(index)
+        })?;
+        self.slot.store(index + 1, Ordering::Release);
+        device::enable_interrupt(interrupt, priority);
+        Ok(())
+    }
+    /// Has to be called from the handler of the interrupt given to
+    /// [start](InterruptExecutor::start)
+    #[inline]
+    pub fn on_interrupt(&'static self) {
+        run_interrupt_executor(self.slot.load(Ordering::Acquire))
+    }
+    /// Spawns tasks on this executor.
+    /// Spawning fails with ``RuntimeError::UninitializedAccess`` until the
+    /// executor is started.
+    #[inline]
+    pub fn spawner(&'static self) -> InterruptSpawner {
+        match self.slot.load(Ordering::Acquire) {
+            0 => InterruptSpawner::on(SLOTS),
+            slot => InterruptSpawner::on(slot),
+        }
+    }
+    /// Panics if the spawn queue is full or the executor is not started,
+    /// see [Spawner::spawn]
+    pub fn spawn<T: Send + 'static>(&'static self, task: SendTask<T>) -> JoinHandle<T> {
+        self.spawner().spawn(task).expect("cannot spawn")
+    }
+}
+
+/// Entry of the task queue.
+/// The derived ordering sorts by deadline first, by priority second and by
+/// enqueue order last, so tasks with the same priority are polled in FIFO
+/// order. The deadline is zero for all tasks unless the executor schedules
+/// by [Scheduling::EarliestDeadline].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct QueuedTask {
+    deadline: u64,
+    priority: Priority,
+    sequence: usize,
+    id: TaskID,
+}
+
+/// The collections of the executor grow on the heap like the tasks do
+#[cfg(not(feature = "task-pool"))]
+mod storage {
+    use super::{QueuedTask, RawTask, TaskID};
+    use alloc::collections::{BTreeMap, BinaryHeap};
+    use core::cmp::Reverse;
+
+    pub(super) type TaskMap = BTreeMap<TaskID, RawTask>;
+    pub(super) type List<T> = alloc::vec::Vec<T>;
+
+    /// T = QueuedTask, lowest first
+    #[derive(Default)]
+    pub(super) struct TaskQueue(BinaryHeap<Reverse<QueuedTask>>);
+
+    impl TaskQueue {
+        #[inline]
+        pub(super) fn push(&mut self, entry: QueuedTask) {
+            self.0.push(Reverse(entry))
+        }
+        #[inline]
+        pub(super) fn pop(&mut self) -> Option<QueuedTask> {
+            self.0.pop().map(|Reverse(entry)| entry)
+        }
+        #[inline]
+        pub(super) fn len(&self) -> usize {
+            self.0.len()
+        }
+        #[inline]
+        pub(super) fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+        /// Unbounded
+        #[inline]
+        pub(super) fn capacity(&self) -> Option<usize> {
+            None
+        }
+    }
+
+    #[inline]
+    pub(super) fn insert_task(tasks: &mut TaskMap, task: RawTask) {
+        tasks.insert(task.id(), task);
+    }
+    #[inline]
+    pub(super) fn push<T>(list: &mut List<T>, entry: T) {
+        list.push(entry)
+    }
+    /// Keeps only the entries for which ``keep`` returns true
+    #[inline]
+    pub(super) fn retain<T>(list: &mut List<T>, keep: impl FnMut(&T) -> bool) {
+        list.retain(keep)
+    }
+}
+
+/// Without a heap the collections of the executor are bounded:
+/// at most 64 tasks and 64 wakers waiting for events or timers
+#[cfg(feature = "task-pool")]
+mod storage {
+    use super::{QueuedTask, RawTask, TaskID};
+    use heapless::binary_heap::{BinaryHeap, Min};
+    use heapless::consts::*;
+    use heapless::LinearMap;
+
+    type MaxTasks = U64;
+    type MaxWakers = U64;
+
+    pub(super) type TaskMap = LinearMap<TaskID, RawTask, MaxTasks>;
+    pub(super) type List<T> = heapless::Vec<T, MaxWakers>;
+
+    /// T = QueuedTask, lowest first.
+    /// A task is queued at most once, so it cannot hold more entries than tasks.
+    #[derive(Default)]
+    pub(super) struct TaskQueue(BinaryHeap<QueuedTask, MaxTasks, Min>);
+
+    impl TaskQueue {
+        #[inline]
+        pub(super) fn push(&mut self, entry: QueuedTask) {
+            self.0.push(entry).expect("task queue full")
+        }
+        #[inline]
+        pub(super) fn pop(&mut self) -> Option<QueuedTask> {
+            self.0.pop()
+        }
+        #[inline]
+        pub(super) fn len(&self) -> usize {
+            self.0.len()
+        }
+        #[inline]
+        pub(super) fn is_empty(&self) -> bool {
+            self.0.is_empty()
+        }
+        #[inline]
+        pub(super) fn capacity(&self) -> Option<usize> {
+            Some(self.0.capacity())
+        }
+    }
+
+    pub(super) fn insert_task(tasks: &mut TaskMap, task: RawTask) {
+        if tasks.insert(task.id(), task).is_err() {
+            panic!("too many tasks")
+        }
+    }
+    pub(super) fn push<T>(list: &mut List<T>, entry: T) {
+        if list.push(entry).is_err() {
+            panic!("too many wakers")
+        }
+    }
+    /// Keeps only the entries for which ``keep`` returns true.
+    /// The order of the remaining entries is not preserved.
+    pub(super) fn retain<T>(list: &mut List<T>, mut keep: impl FnMut(&T) -> bool) {
+        let mut i = 0;
+        while i < list.len() {
+            if keep(&list[i]) {
+                i += 1;
+            } else {
+                list.swap_remove(i);
+            }
+        }
+    }
+}
+use storage::{push, retain};
+
+/// Polls per pass of ``Executor::run`` if not configured otherwise
+const DEFAULT_POLL_BUDGET: usize = 32;
+/// A job misses its deadline once the clock is past it,
+/// so the deadline timer fires a tick later
+const MISS_AFTER: Duration = Duration::from_nanos(1);
+/// Slow polls in a row after which a task is logged
+const SLOW_POLL_REPEAT: u32 = 3;
 
 pub struct Executor {
-    tasks: BTreeMap<TaskID, Task>,
-    /// T = TaskID, max length = 256, index type = u8
-    task_queue: Queue<TaskID, U256, u8, SingleCore>, //TODO: Multicore on feature
+    /// Index into the queues, see [SLOTS]
+    slot: usize,
+    tasks: TaskMap,
+    /// Lowest priority value first
+    task_queue: TaskQueue, //TODO: Multicore on feature
+    /// Enqueue counter to keep the FIFO order inside a priority level
+    sequence: usize,
     /// If an event is fired, these wakers requeue the corresponding tasks
-    event_wakers: BTreeMap<Event, Vec<Waker>>,
+    /// A task has at most one waker per event, see [insert_waker]
+    event_wakers: List<(Event, Waker)>,
+    /// Wakers of sleeping tasks with their deadline, at most one per task
+    timers: List<(Instant, Waker)>,
+    /// Maximum length the task queue reached
+    queue_high_water: usize,
+    /// Polls per pass, afterwards ``run`` returns even if tasks are ready.
+    /// Tasks that wake themselves all the time cannot keep the runtime
+    /// from idling and its other duties this way.
+    poll_budget: usize,
+    /// Polls that take at least this long (in µs) count as slow
+    slow_poll_us: Option<u64>,
+    scheduling: Scheduling,
+    /// Deadline misses of all tasks, including the removed ones
+    deadline_misses: u32,
 }
 
 impl Executor {
+    /// The thread-mode executor
     #[inline]
     pub fn new() -> Executor {
+        Self::with_slot(THREAD)
+    }
+    fn with_slot(slot: usize) -> Executor {
         Executor {
-            tasks: BTreeMap::new(),
-            task_queue: unsafe { Queue::u8_sc() },
-            event_wakers: BTreeMap::new(),
+            slot,
+            tasks: TaskMap::new(),
+            task_queue: TaskQueue::default(),
+            sequence: 0,
+            event_wakers: List::new(),
+            timers: List::new(),
+            queue_high_water: 0,
+            poll_budget: DEFAULT_POLL_BUDGET,
+            slow_poll_us: None,
+            scheduling: Scheduling::default(),
+            deadline_misses: 0,
+        }
+    }
+
+    fn insert(&mut self, mut task: RawTask) {
+        task.release(Instant::now());
+        let (id, priority, timing) = (task.id(), task.priority(), task.timing());
+        let deadline = deadline_key(self.scheduling, &task);
+        storage::insert_task(&mut self.tasks, task);
+        self.enqueue(id, deadline, priority);
+        if let Some(timing) = timing {
+            self.watch_deadline(&timing);
         }
     }
 
-    pub fn spawn(&mut self, task: Task) {
-        self.task_queue.enqueue(task.id()).expect("task queue full");
-        self.tasks.insert(task.id(), task);
+    /// Wakers can outlive their task, waking a removed task is a no-op.
+    /// A task that is already queued is not queued twice.
+    #[inline]
+    fn wake(&mut self, task_id: TaskID, reason: WakeReason) {
+        if let Some(task) = self.tasks.get_mut(&task_id) {
+            task.woken(reason);
+            if task.enqueued() {
+                let deadline = deadline_key(self.scheduling, task);
+                let priority = task.priority();
+                self.enqueue(task_id, deadline, priority)
+            }
+        }
+    }
+    /// Takes over spawned and woken tasks
+    fn drain_queues(&mut self) {
+        let queues = &QUEUES[self.slot];
+        queues.pending.store(false, Ordering::Release);
+        self.take_registrations();
+        while let Some(SpawnedTask(task)) = queues.spawn.dequeue() {
+            self.insert(task)
+        }
+        while let Some((task_id, reason)) = queues.wake.dequeue() {
+            self.wake(task_id, reason)
+        }
+        if queues.wake_overflow.swap(false, Ordering::AcqRel) {
+            let ids: List<TaskID> = self.tasks.keys().cloned().collect();
+            for task_id in ids {
+                self.wake(task_id, WakeReason::Waker)
+            }
+        }
+    }
+    /// Takes over the timers and, in thread mode, the event wakers that
+    /// were registered since the last time.
+    /// It happens before timers and events are checked, so no wake is lost.
+    fn take_registrations(&mut self) {
+        let mut timers = false;
+        interrupt::free(|cs| {
+            let mut registered = REGISTERED_TIMERS.borrow(cs).borrow_mut();
+            if let Some(registered) = registered[self.slot].as_mut() {
+                while let Some((deadline, waker)) = registered.pop() {
+                    self.add_timer(deadline, waker);
+                    timers = true;
+                }
+            }
+        });
+        if timers {
+            self.arm_alarm();
+        }
+        if self.slot == THREAD {
+            interrupt::free(|cs| {
+                if let Some(registered) = REGISTERED_WAKERS.borrow(cs).borrow_mut().as_mut() {
+                    while let Some((trigger, waker)) = registered.pop() {
+                        insert_waker(&mut self.event_wakers, &trigger, &waker)
+                    }
+                }
+            });
+        }
     }
     #[inline]
-    pub(crate) fn reque(&mut self, task_id: TaskID) {
-        self.task_queue.enqueue(task_id).expect("task queue full")
+    fn enqueue(&mut self, id: TaskID, deadline: u64, priority: Priority) {
+        let sequence = self.sequence;
+        self.sequence = self.sequence.wrapping_add(1);
+        self.task_queue.push(QueuedTask {
+            deadline,
+            priority,
+            sequence,
+            id,
+        });
+        self.queue_high_water = self.queue_high_water.max(self.task_queue.len());
     }
+    /// Polls ready tasks until none is left or the poll budget is used up.
+    /// In the latter case the executor stays pending, so the device does
+    /// not sleep before the next pass.
+    /// Interrupt executors pend their interrupt again instead.
     pub fn run(&mut self) {
+        let thread = self.slot == THREAD;
+        if thread {
+            RUNNING.store(true, Ordering::Release);
+        }
+        let mut polls = 0;
         loop {
-            self.wake_tasks();
-            if let Some(task_id) = self.task_queue.dequeue() {
-                let task = self.tasks.get_mut(&task_id).expect("missing task");
+            self.take_registrations();
+            if thread {
+                #[cfg(feature = "host")]
+                run_pended();
+                self.wake_tasks();
+            } else {
+                self.wake_due_timers();
+            }
+            self.drain_queues();
+            if polls == self.poll_budget {
+                if !self.task_queue.is_empty() {
+                    notify(self.slot);
+                }
+                break;
+            }
+            if let Some(QueuedTask { id: task_id, .. }) = self.task_queue.pop() {
+                if thread {
+                    self.refresh_counters();
+                }
+                // the task may have been removed after it was queued
+                let task = match self.tasks.get_mut(&task_id) {
+                    Some(task) => task,
+                    None => continue,
+                };
+                task.dequeued();
+                if task.check_deadline(Instant::now()) {
+                    self.deadline_misses = self.deadline_misses.wrapping_add(1);
+                }
+                polls += 1;
                 let waker = task.waker();
                 let mut context = Context::from_waker(&waker);
-                match task.poll(&mut context) {
-                    Poll::Ready(()) => {} // task done
+                let timing = task.timing();
+                set_current_timing(self.slot, timing);
+                #[cfg(feature = "trace")]
+                crate::trace::record(
+                    crate::trace::Kind::PollStart,
+                    self.slot,
+                    task_id.into_raw() as u16,
+                );
+                let poll = task.poll(&mut context);
+                #[cfg(feature = "trace")]
+                crate::trace::record(
+                    match poll {
+                        Poll::Ready(()) => crate::trace::Kind::PollReady,
+                        Poll::Pending => crate::trace::Kind::PollEnd,
+                    },
+                    self.slot,
+                    task_id.into_raw() as u16,
+                );
+                let completed = set_current_timing(self.slot, None);
+                task.set_timing(completed);
+                if let Some(threshold) = self.slow_poll_us {
+                    if task.count_slow_poll(threshold) == SLOW_POLL_REPEAT {
+                        log::warn!(
+                            "task {} took at least {} us in {} polls in a row",
+                            task_id,
+                            threshold,
+                            SLOW_POLL_REPEAT
+                        );
+                    }
+                }
+                if let (Some(before), Some(after)) = (timing, completed) {
+                    self.deadline_misses = self
+                        .deadline_misses
+                        .wrapping_add(after.misses.wrapping_sub(before.misses));
+                    // the task completed a job, the next one has a new deadline
+                    if after.jobs != before.jobs {
+                        self.watch_deadline(&after);
+                    }
+                }
+                match poll {
+                    Poll::Ready(()) => self.remove(task_id), // task done or aborted
                     Poll::Pending => {}
                 }
                 continue; // test for additional events and task before termination
             }
             break;
         }
+        if thread {
+            self.refresh_snapshot();
+            RUNNING.store(false, Ordering::Release);
+        }
+    }
+    /// Copies the statistics for the ``sys:`` resources, see [Snapshot]
+    fn refresh_snapshot(&self) {
+        let mut tasks = self.tasks.values();
+        for index in 0..SNAPSHOT_TASKS {
+            let task = tasks.next().map(|task| TaskSnapshot {
+                id: task.id(),
+                priority: task.priority(),
+                stats: task.stats().clone(),
+                timing: task.timing(),
+            });
+            interrupt::free(|cs| SNAPSHOT.borrow(cs).borrow_mut().tasks[index] = task);
+        }
+        self.refresh_counters();
+    }
+    #[inline]
+    fn refresh_counters(&self) {
+        let counters = Counters {
+            queue_len: self.task_queue.len(),
+            queue_capacity: self.task_queue.capacity(),
+            queue_high_water: self.queue_high_water,
+            deadline_misses: self.deadline_misses,
+        };
+        interrupt::free(|cs| SNAPSHOT.borrow(cs).borrow_mut().counters = counters)
+    }
+    /// Drops the task and all wakers it left at the executor
+    fn remove(&mut self, task_id: TaskID) {
+        self.take_registrations();
+        let waker = match self.tasks.get(&task_id) {
+            Some(task) => task.waker(),
+            None => return,
+        };
+        retain(&mut self.event_wakers, |(_, registered)| {
+            !registered.will_wake(&waker)
+        });
+        retain(&mut self.timers, |(_, registered)| {
+            !registered.will_wake(&waker)
+        });
+        self.tasks.remove(&task_id);
+        self.arm_alarm();
     }
     #[inline]
     fn wake_tasks(&mut self) {
-        while let Some(event) = events::next() {
-            if let Some(wakers) = self.event_wakers.get_mut(&event) {
-                // only do event specific things if someone actuall is expecting events
+        while let Some(timed) = events::next() {
+            let event = &timed.event;
+            if let Event::Timer = event {
+                self.wake_timers();
+                continue;
+            }
+            // resources record every event, also for subscribers that are busy right now
+            if !Self::handle_event(&timed) {
+                continue;
+            }
+            if self
+                .event_wakers
+                .iter()
+                .any(|(trigger, _)| trigger == event)
+            {
                 // log::info!("E");
-                Self::handle_event(&event);
-                while let Some(waker) = wakers.pop() {
-                    waker.wake_by_ref()
-                }
+                set_wake_reason(WakeReason::Event(event.clone()));
+                retain(&mut self.event_wakers, |(trigger, waker)| {
+                    if trigger == event {
+                        waker.wake_by_ref();
+                        false
+                    } else {
+                        true
+                    }
+                });
+                set_wake_reason(WakeReason::Waker);
             }
         }
     }
 
-    /// Trigger event specific behaviour
+    /// Trigger event specific behaviour.
+    /// Returns false if the event was dropped and nobody has to be woken.
     #[inline]
-    fn handle_event(event: &Event) {
-        match event {
-            Event::ExternalInterrupt(exti_event) => handle_exti_event(exti_event),
-            Event::DeviceInterrupt => {}
+    fn handle_event(timed: &TimedEvent) -> bool {
+        match &timed.event {
+            Event::ExternalInterrupt(exti_event) => handle_exti_event(exti_event, timed),
+            Event::User(index) => match Resources::get_user_event(*index) {
+                Ok(resource) => resource.handle_event(timed),
+                Err(_) => true,
+            },
+            Event::DeviceInterrupt => true,
+            Event::Timer => true,
         }
     }
 
-    pub(crate) fn register_waker(&mut self, trigger: &Event, waker: &Waker) {
-        let wakers = if let Some(w) = self.event_wakers.get_mut(trigger) {
-            w
-        } else {
-            self.event_wakers.insert(trigger.clone(), Vec::new());
-            self.event_wakers.get_mut(trigger).unwrap()
-        };
-        wakers.push(waker.clone());
+    /// Wake all tasks whose deadline is reached and arm the alarm for the next one.
+    /// Wakes from interrupt executors are recorded as interrupt wakes anyway,
+    /// so only the thread-mode executor sets the wake reason.
+    fn wake_timers(&mut self) {
+        let now = Instant::now();
+        let thread = self.slot == THREAD;
+        if thread {
+            set_wake_reason(WakeReason::Timer);
+        }
+        retain(&mut self.timers, |(deadline, waker)| {
+            if *deadline <= now {
+                waker.wake_by_ref();
+                false
+            } else {
+                true
+            }
+        });
+        if thread {
+            set_wake_reason(WakeReason::Waker);
+        }
+        self.check_deadlines(now);
+        self.arm_alarm();
+    }
+
+    /// Counts the misses of all jobs whose deadline passed, also of the
+    /// jobs that wait for something and are not polled, and watches the
+    /// earliest deadline that is left
+    fn check_deadlines(&mut self, now: Instant) {
+        let mut earliest = None;
+        for task in self.tasks.values_mut() {
+            if task.check_deadline(now) {
+                self.deadline_misses = self.deadline_misses.wrapping_add(1);
+            }
+            if let Some(deadline) = task.timing().and_then(|timing| timing.pending_deadline()) {
+                earliest =
+                    Some(earliest.map_or(deadline, |earliest: Instant| earliest.min(deadline)));
+            }
+        }
+        if let Some(deadline) = earliest {
+            self.add_timer(deadline + MISS_AFTER, deadline_waker());
+        }
+    }
+
+    /// Arms a timer for the deadline of the current job of a task,
+    /// see [Executor::check_deadlines]
+    fn watch_deadline(&mut self, timing: &Timing) {
+        if let Some(deadline) = timing.pending_deadline() {
+            self.add_timer(deadline + MISS_AFTER, deadline_waker());
+            self.arm_alarm();
+        }
+    }
+
+    /// Interrupt executors do not see the ``Event::Timer``,
+    /// they check their timers on every pass instead
+    #[inline]
+    fn wake_due_timers(&mut self) {
+        let now = Instant::now();
+        if self.timers.iter().any(|(deadline, _)| *deadline <= now) {
+            self.wake_timers()
+        }
+    }
+
+    /// The device has a single alarm, it is set to the earliest timer of
+    /// all executors
+    #[inline]
+    fn arm_alarm(&self) {
+        let earliest = self
+            .timers
+            .iter()
+            .map(|(deadline, _)| deadline.ticks())
+            .min()
+            .unwrap_or(u64::MAX);
+        interrupt::free(|cs| {
+            let mut deadlines = TIMER_DEADLINES.borrow(cs).borrow_mut();
+            deadlines[self.slot] = earliest;
+            device::set_alarm(deadlines.iter().cloned().min().unwrap_or(u64::MAX))
+        })
+    }
+
+    /// Registers a timer from outside of the tasks, e.g. for the watchdog
+    pub(crate) fn register_timer(&mut self, deadline: Instant, waker: &Waker) {
+        self.add_timer(deadline, waker.clone());
+        self.arm_alarm();
+    }
+
+    #[inline]
+    fn add_timer(&mut self, deadline: Instant, waker: Waker) {
+        insert_timer(&mut self.timers, deadline, waker)
+    }
+    /// Only affects tasks queued afterwards,
+    /// so it should be selected before tasks are spawned
+    pub(crate) fn set_scheduling(&mut self, scheduling: Scheduling) {
+        self.scheduling = scheduling;
+    }
+
+    /// At least one poll per pass
+    pub(crate) fn set_poll_budget(&mut self, budget: usize) {
+        self.poll_budget = budget.max(1);
+    }
+    pub(crate) fn set_slow_poll_threshold(&mut self, threshold: Option<Duration>) {
+        self.slow_poll_us = threshold.map(|threshold| threshold.as_micros() as u64);
+    }
+}
+
+/// Keeps a single timer per waker with the earliest deadline, so a task
+/// that awaits many timers at once (e.g. a ``join`` of sleeps) holds one.
+/// A timer that fires early (e.g. the one of a dropped ``timeout``) only
+/// causes a spurious wake, pending timer futures register again when
+/// they are polled.
+fn insert_timer(timers: &mut List<(Instant, Waker)>, deadline: Instant, waker: Waker) {
+    match timers
+        .iter_mut()
+        .find(|(_, registered)| registered.will_wake(&waker))
+    {
+        Some(timer) => {
+            if deadline < timer.0 {
+                timer.0 = deadline
+            }
+        }
+        None => push(timers, (deadline, waker)),
+    }
+}
+
+/// Registering the same waker for the same event again does not add an entry
+fn insert_waker(wakers: &mut List<(Event, Waker)>, trigger: &Event, waker: &Waker) {
+    if !wakers
+        .iter()
+        .any(|(registered, other)| registered == trigger && other.will_wake(waker))
+    {
+        push(wakers, (trigger.clone(), waker.clone()))
+    }
+}
+
+/// Deadline of ``task`` in the task queue
+#[inline]
+fn deadline_key(scheduling: Scheduling, task: &RawTask) -> u64 {
+    match scheduling {
+        Scheduling::Priority => 0,
+        Scheduling::EarliestDeadline => task.deadline_ticks(),
     }
 }
@@ -1,4 +1,4 @@
-#![no_std]
+#![cfg_attr(not(feature = "host"), no_std)]
 #![feature(alloc_error_handler)]
 
 extern crate alloc;
@@ -37,12 +37,24 @@ pub mod device;
 
 pub mod events;
 pub mod io;
+#[cfg(feature = "task-pool")]
+pub mod pool;
+pub mod power;
 pub mod resources;
 pub mod schemes;
+pub mod supervisor;
+pub mod sync;
+pub mod time;
+#[cfg(feature = "trace")]
+pub mod trace;
+pub mod watchdog;
 
-pub use task::Task;
+pub use executor::InterruptExecutor;
+pub use task::{
+    next_period, yield_now, InterruptSpawner, JoinError, JoinHandle, NextPeriod, Priority,
+    Scheduling, SendTask, Spawner, Task, TaskID, YieldNow,
+};
 
-use alloc::boxed::Box;
 use core::task::Waker;
 use core::{
     future::Future,
@@ -50,11 +62,12 @@ use core::{
     task::{Context, Poll},
 };
 use events::Event;
-use resources::{Resource, ResourceError, ResourceID, Resources};
+use resources::{Resource, ResourceError, ResourceID, Resources, UserEvent};
 
 pub struct Runtime {
     resources: Resources,
     executor: executor::Executor,
+    idle_policy: power::IdlePolicy,
 }
 
 #[non_exhaustive]
@@ -64,12 +77,28 @@ pub enum RuntimeError {
     MultipleInitializations,
     ResourceNotFound,
     TaskQueueIsFull,
+    /// All check-in slots of the [watchdog] are in use
+    TooManyCriticalTasks,
+    /// All slots for [InterruptExecutor]s are in use
+    TooManyExecutors,
+    /// The interrupt priority is not implemented by the NVIC
+    InvalidPriority,
+    /// The core clock cannot drive the monotonic clock, e.g. the SysTick
+    /// reload for a tick is zero or does not fit into its 24 bits
+    InvalidClock,
+    /// All slots of a [TaskPool](pool::TaskPool) are in use
+    TaskPoolExhausted,
     UriParseError,
 }
 
 impl Runtime {
+    /// ``sys_clock`` is the core clock in hertz, it drives the monotonic clock.
+    /// Fails with ``RuntimeError::InvalidClock`` if the device cannot derive
+    /// the ticks from it, e.g. a core clock below 2 kHz.
+    /// The heap is only initialized if ``heap_size`` is not zero.
     pub fn init(
         heap_size: usize,
+        sys_clock: u32,
         sys: &'static mut [&'static mut dyn Resource],
         input_pins: &'static mut [&'static mut dyn Resource],
         output_pins: &'static mut [&'static mut dyn Resource],
@@ -77,20 +106,43 @@ impl Runtime {
         channels: &'static mut [&'static mut dyn Resource],
         serials: &'static mut [&'static mut dyn Resource],
         timers: &'static mut [&'static mut dyn Resource],
+        user_events: &'static mut [UserEvent],
     ) -> Result<&'static mut Self, RuntimeError> {
         let inner = Self::get_inner();
         if let Some(_) = inner {
             return Err(RuntimeError::MultipleInitializations);
         };
-        device::init_heap(device::heap_bottom(), heap_size);
+        if !device::valid_clock(sys_clock) {
+            return Err(RuntimeError::InvalidClock);
+        }
+        if heap_size > 0 {
+            device::init_heap(device::heap_bottom(), heap_size);
+        }
         logging::init().expect("log initialization failed");
         inner.replace(Self {
             executor: executor::Executor::new(),
-            resources: Resources::new(sys, input_pins, output_pins, pwm, channels, serials, timers),
+            resources: Resources::new(
+                sys,
+                input_pins,
+                output_pins,
+                pwm,
+                channels,
+                serials,
+                timers,
+                user_events,
+            ),
+            idle_policy: power::IdlePolicy::default(),
         });
+        device::init_clock(sys_clock);
+        supervisor::init();
         let rt = Self::get();
         Ok(rt)
     }
+    /// Drops the runtime, so the next simulation can initialize it again
+    #[cfg(feature = "host")]
+    pub(crate) fn reset() {
+        Self::get_inner().take();
+    }
     #[inline]
     fn get_inner() -> &'static mut Option<Runtime> {
         static mut RUNTIME: Option<Runtime> = None;
@@ -110,16 +162,88 @@ impl Runtime {
     pub fn get_resource(&'static mut self, uri: &str) -> Result<ResourceID, ResourceError> {
         self.resources.get_resource(uri)
     }
+    /// Selects how the core sleeps while no task is ready,
+    /// [IdlePolicy::WaitForEvent](power::IdlePolicy::WaitForEvent) by default
+    pub fn set_idle_policy(&'static mut self, policy: power::IdlePolicy) {
+        self.idle_policy = policy;
+    }
+    /// Selects how the executor orders ready tasks,
+    /// [Scheduling::Priority] by default.
+    /// Tasks that are already queued keep their position.
+    pub fn set_scheduling(&'static mut self, scheduling: Scheduling) {
+        self.executor.set_scheduling(scheduling)
+    }
+    /// Limits the polls per pass of the executor, 32 by default.
+    /// The runtime checks for events and idles in between passes.
+    pub fn set_poll_budget(&'static mut self, budget: usize) {
+        self.executor.set_poll_budget(budget)
+    }
+    /// Logs a warning if a task takes at least ``threshold`` for several
+    /// polls in a row. Slow polls are also counted in ``sys:sys/tasks``.
+    /// ``None`` (the default) disables the check.
+    pub fn set_slow_poll_threshold(&'static mut self, threshold: Option<core::time::Duration>) {
+        self.executor.set_slow_poll_threshold(threshold)
+    }
     pub fn run(&'static mut self) -> ! {
         loop {
             self.executor.run();
-            crate::device::sleep();
+            watchdog::service(&mut self.executor);
+            device::idle(&self.idle_policy, executor::pending);
         }
     }
-    pub fn spawn_task(&'static mut self, task: Task) {
-        self.executor.spawn(task);
+    /// Polls tasks until all of them wait for something else than a timer.
+    /// Timers expire right away because the simulated clock jumps to the
+    /// next deadline. Tasks that sleep periodically keep this running forever,
+    /// use [run_for](Runtime::run_for) for them.
+    /// The watchdog is not fed.
+    #[cfg(feature = "host")]
+    pub fn run_until_idle(&'static mut self) {
+        loop {
+            self.executor.run();
+            if executor::pending() {
+                continue;
+            }
+            match device::host::alarm() {
+                u64::MAX => break,
+                tick => device::host::advance_to(tick),
+            }
+        }
+    }
+    /// Polls tasks while the simulated clock advances by ``duration``
+    #[cfg(feature = "host")]
+    pub fn run_for(&'static mut self, duration: core::time::Duration) {
+        let end = (time::Instant::now() + duration).ticks();
+        loop {
+            self.executor.run();
+            watchdog::service(&mut self.executor);
+            if executor::pending() {
+                continue;
+            }
+            let alarm = device::host::alarm();
+            if alarm > end {
+                device::host::advance_to(end);
+                break;
+            }
+            device::host::advance_to(alarm);
+        }
+    }
+    /// Panics if the spawn queue is full, see [Spawner::spawn]
+    pub fn spawn_task<T: 'static>(&'static mut self, task: Task<T>) -> JoinHandle<T> {
+        Self::spawner().spawn(task).expect("spawn queue full")
+    }
+    /// The spawner can be used from tasks and interrupt handlers
+    #[inline]
+    pub fn spawner() -> Spawner {
+        Spawner::new()
+    }
+    /// Events are always dispatched by the thread-mode executor.
+    /// Like the timers, wakers are registered without borrowing the runtime,
+    /// the executor polls the task that registers them.
+    pub(crate) fn register_waker(trigger: &Event, waker: &Waker) {
+        executor::register_waker(trigger, waker)
     }
-    pub(crate) fn register_waker(&'static mut self, trigger: &Event, waker: &Waker) {
-        self.executor.register_waker(trigger, waker)
+    /// The timer is registered at the executor of the current task
+    pub(crate) fn register_timer(deadline: time::Instant, waker: &Waker) {
+        executor::register_timer(deadline, waker)
     }
 }
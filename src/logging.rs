@@ -6,6 +6,7 @@ struct SimpleLogger;
 static LOGGER: SimpleLogger = SimpleLogger;
 
 // TODO: Multicore on feature
+#[cfg(not(feature = "host"))]
 pub fn init() -> Result<(), SetLoggerError> {
     unsafe {
         if cfg!(debug_assertions) {
@@ -16,6 +17,18 @@ pub fn init() -> Result<(), SetLoggerError> {
     }
 }
 
+/// Every simulation initializes the runtime again, the logger stays
+#[cfg(feature = "host")]
+pub fn init() -> Result<(), SetLoggerError> {
+    let _ = log::set_logger(&LOGGER);
+    if cfg!(debug_assertions) {
+        log::set_max_level(LevelFilter::Trace)
+    } else {
+        log::set_max_level(LevelFilter::Info)
+    }
+    Ok(())
+}
+
 impl log::Log for SimpleLogger {
     fn enabled(&self, metadata: &Metadata) -> bool {
         if cfg!(debug_assertions) {
@@ -27,8 +40,10 @@ impl log::Log for SimpleLogger {
 
     fn log(&self, record: &Record) {
         if self.enabled(record.metadata()) {
-            use cortex_m_semihosting::hprintln;
-            hprintln!("{}: {}", record.level(), record.args()).unwrap();
+            #[cfg(not(feature = "host"))]
+            cortex_m_semihosting::hprintln!("{}: {}", record.level(), record.args()).unwrap();
+            #[cfg(feature = "host")]
+            std::eprintln!("{}: {}", record.level(), record.args());
         }
     }
 
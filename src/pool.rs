@@ -0,0 +1,239 @@
+//! Statically allocated task storage for the ``task-pool`` feature.
+//!
+//! Every [TaskPool] is a static array of equally sized slots. A slot holds
+//! the future of one task together with the state it shares with its
+//! [JoinHandle](crate::JoinHandle). It is freed as soon as the task finished
+//! and its handle was dropped, so no heap is needed to spawn tasks.
+//!
+//! Pools are usually generated by the ``task`` attribute of the macro crate:
+//! ```ignore
+//! #[task(pool_size = 2)]
+//! async fn blink(period: Duration) { ... }
+//!
+//! blink(Duration::from_millis(500))?.spawn();
+//! ```
+//! The attribute turns the function into one that returns
+//! ``Result<Task<_>, RuntimeError>`` and fails with
+//! ``RuntimeError::TaskPoolExhausted`` if all slots are in use.
+use crate::device::interrupt::Mutex;
+use crate::task::{JoinCell, JoinShared, Joinable, SendTask, Shared};
+use crate::{RuntimeError, Task};
+use core::{
+    cell::{RefCell, UnsafeCell},
+    future::Future,
+    mem::{align_of, size_of, MaybeUninit},
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    ptr::{self, NonNull},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Alignment of every slot. Futures with a higher alignment cannot be pooled.
+const SLOT_ALIGN: usize = 8;
+
+/// ``N`` slots of ``SIZE`` bytes each.
+/// [slot_size] returns the size a task function needs.
+pub struct TaskPool<const SIZE: usize, const N: usize> {
+    slots: [Slot<SIZE>; N],
+}
+
+struct Slot<const SIZE: usize> {
+    /// References to the join state, zero if the slot is free
+    refs: AtomicUsize,
+    storage: UnsafeCell<Storage<SIZE>>,
+}
+
+#[repr(C, align(8))]
+struct Storage<const SIZE: usize>(MaybeUninit<[u8; SIZE]>);
+
+/// The content of a slot.
+/// Both fields are dropped on their own: the future when the task is
+/// removed and the join state when the last reference to it is dropped.
+#[repr(C)]
+struct PoolTask<F: Future> {
+    shared: JoinCell<F::Output>,
+    future: Joinable<F>,
+}
+
+// Slots are claimed atomically and the content of a slot is only accessed
+// through the task and the JoinHandle that own it.
+unsafe impl<const SIZE: usize, const N: usize> Sync for TaskPool<SIZE, N> {}
+
+impl<const SIZE: usize, const N: usize> TaskPool<SIZE, N> {
+    const FREE: Slot<SIZE> = Slot {
+        refs: AtomicUsize::new(0),
+        storage: UnsafeCell::new(Storage(MaybeUninit::uninit())),
+    };
+
+    pub const fn new() -> Self {
+        TaskPool {
+            slots: [Self::FREE; N],
+        }
+    }
+
+    /// Places ``future`` in a free slot.
+    /// Panics if the future does not fit in a slot.
+    pub fn task<F>(&'static self, future: F) -> Result<Task<F::Output>, RuntimeError>
+    where
+        F: Future + 'static,
+    {
+        assert!(
+            size_of::<PoolTask<F>>() <= SIZE && align_of::<PoolTask<F>>() <= SLOT_ALIGN,
+            "future does not fit in the task pool"
+        );
+        let slot = self
+            .slots
+            .iter()
+            .find(|slot| {
+                slot.refs
+                    .compare_exchange(0, 1, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            })
+            .ok_or(RuntimeError::TaskPoolExhausted)?;
+        unsafe {
+            let task = slot.storage.get() as *mut PoolTask<F>;
+            let shared = ptr::addr_of_mut!((*task).shared);
+            shared.write(Mutex::new(RefCell::new(JoinShared::new())));
+            let shared: Shared<F::Output> = PoolRc {
+                value: NonNull::new_unchecked(shared),
+                refs: &slot.refs,
+            };
+            let joinable = ptr::addr_of_mut!((*task).future);
+            joinable.write(Joinable::new(future, shared.clone()));
+            // the slot is static, so the future never moves
+            let future = Pin::new_unchecked(PoolBox(NonNull::new_unchecked(
+                joinable as *mut dyn Future<Output = ()>,
+            )));
+            Ok(Task::from_parts(future, shared))
+        }
+    }
+
+    /// Places ``future`` in a free slot for an
+    /// [InterruptExecutor](crate::InterruptExecutor), see [task](TaskPool::task)
+    pub fn send_task<F>(&'static self, future: F) -> Result<SendTask<F::Output>, RuntimeError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send,
+    {
+        // the future and its output are Send
+        self.task(future)
+            .map(|task| unsafe { SendTask::from_task(task) })
+    }
+
+    /// Number of slots in use
+    pub fn used(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|slot| slot.refs.load(Ordering::Relaxed) != 0)
+            .count()
+    }
+
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+/// Used by the ``task`` attribute to size the slots of a pool
+pub trait TaskFn<Args> {
+    type Future: Future;
+}
+
+macro_rules! task_fn {
+    ($($arg:ident),*) => {
+        impl<Func, Fut, $($arg),*> TaskFn<($($arg,)*)> for Func
+        where
+            Func: Fn($($arg),*) -> Fut,
+            Fut: Future,
+        {
+            type Future = Fut;
+        }
+    };
+}
+task_fn!();
+task_fn!(A);
+task_fn!(A, B);
+task_fn!(A, B, C);
+task_fn!(A, B, C, D);
+task_fn!(A, B, C, D, E);
+task_fn!(A, B, C, D, E, G);
+task_fn!(A, B, C, D, E, G, H);
+task_fn!(A, B, C, D, E, G, H, I);
+
+/// The slot size needed for the future returned by ``task_fn``
+pub const fn slot_size<Func, Args>(_task_fn: &Func) -> usize
+where
+    Func: TaskFn<Args>,
+{
+    size_of::<PoolTask<Func::Future>>()
+}
+
+/// Owns the future in a pool slot and drops it in place
+pub(crate) struct PoolBox(NonNull<dyn Future<Output = ()>>);
+
+impl Deref for PoolBox {
+    type Target = dyn Future<Output = ()>;
+    fn deref(&self) -> &Self::Target {
+        unsafe { self.0.as_ref() }
+    }
+}
+impl DerefMut for PoolBox {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { self.0.as_mut() }
+    }
+}
+impl Drop for PoolBox {
+    fn drop(&mut self) {
+        unsafe { ptr::drop_in_place(self.0.as_ptr()) }
+    }
+}
+
+/// A reference counted pointer into a pool slot.
+/// Dropping the last reference drops the value and frees the slot.
+pub(crate) struct PoolRc<T> {
+    value: NonNull<T>,
+    refs: &'static AtomicUsize,
+}
+
+// like an Arc, the references can be on executors that preempt each other
+unsafe impl<T: Send + Sync> Send for PoolRc<T> {}
+unsafe impl<T: Send + Sync> Sync for PoolRc<T> {}
+
+impl<T> Clone for PoolRc<T> {
+    fn clone(&self) -> Self {
+        self.refs.fetch_add(1, Ordering::Relaxed);
+        PoolRc {
+            value: self.value,
+            refs: self.refs,
+        }
+    }
+}
+impl<T> Deref for PoolRc<T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { self.value.as_ref() }
+    }
+}
+impl<T> Drop for PoolRc<T> {
+    fn drop(&mut self) {
+        // the slot stays claimed until the value is dropped,
+        // the last reference cannot be cloned concurrently
+        let mut refs = self.refs.load(Ordering::Acquire);
+        loop {
+            if refs == 1 {
+                unsafe { ptr::drop_in_place(self.value.as_ptr()) };
+                self.refs.store(0, Ordering::Release);
+                return;
+            }
+            match self.refs.compare_exchange_weak(
+                refs,
+                refs - 1,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return,
+                Err(current) => refs = current,
+            }
+        }
+    }
+}
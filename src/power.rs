@@ -0,0 +1,42 @@
+//! Low-power idling of the runtime.
+//!
+//! Whenever the executor runs out of ready tasks, [Runtime::run](crate::Runtime::run)
+//! puts the core to sleep as selected by the [IdlePolicy]. Except for
+//! ``WaitForEvent`` the policies are tickless: the monotonic clock does not
+//! wake the core every tick but only when the earliest timer deadline is due.
+//! The skipped ticks are added to the clock after the wakeup.
+//!
+//! ```ignore
+//! let rt = Runtime::get();
+//! rt.set_idle_policy(IdlePolicy::Stop {
+//!     min_idle: Duration::from_millis(20),
+//! });
+//! rt.run();
+//! ```
+use core::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdlePolicy {
+    /// Wait for event (WFE) after every pass of the executor.
+    /// The clock keeps ticking, so the core wakes up every tick.
+    WaitForEvent,
+    /// Wait for interrupt (WFI) with the clock ticks suppressed until the
+    /// next timer deadline.
+    WaitForInterrupt,
+    /// The core only sleeps in interrupt handlers and returns to the executor
+    /// if a handler pushed an event, woke or spawned a task.
+    /// Handlers of the application have to call
+    /// [wake_thread](crate::device::wake_thread) if the executor should run.
+    SleepOnExit,
+    /// Deep sleep (STOP mode on STM32F1) if the next timer deadline is at
+    /// least ``min_idle`` away, tickless WFI otherwise.
+    /// The core clock stops, an RTC alarm wakes the core at the deadline
+    /// and the clock configuration is restored afterwards.
+    Stop { min_idle: Duration },
+}
+
+impl Default for IdlePolicy {
+    fn default() -> Self {
+        IdlePolicy::WaitForEvent
+    }
+}
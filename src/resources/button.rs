@@ -0,0 +1,316 @@
+//! Gestures of a push button on an input pin, read from ``event:button/<pin>``.
+//!
+//! The pin has to trigger on all edges. Each subscriber recognizes the
+//! gestures on its own from the edges of the pin, so the thresholds are set
+//! per uri: ``event:button/pa0?long=800ms&double=250ms&active=low``.
+//! A read returns the [Gesture] as event byte, followed by its timestamp.
+use super::ResourceError;
+use crate::events::{EventCursor, EventLog};
+use crate::time::{self, Timestamp};
+use core::time::Duration;
+
+/// The event byte of a button event
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    Press = 1,
+    Release = 2,
+    /// Released and not pressed again within the double click time
+    Click = 3,
+    /// Pressed again within the double click time, sent after the second release
+    DoubleClick = 4,
+    /// Held for the long press time, no click follows on release.
+    /// This includes the second press of a double click: a click followed
+    /// by a long press reports the long press only.
+    LongPress = 5,
+}
+
+impl Gesture {
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Gesture::Press),
+            2 => Some(Gesture::Release),
+            3 => Some(Gesture::Click),
+            4 => Some(Gesture::DoubleClick),
+            5 => Some(Gesture::LongPress),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ButtonConfig {
+    pub long_press: Duration,
+    pub double_click: Duration,
+    /// The pin level while the button is pressed, low for buttons with a pull up
+    pub active_high: bool,
+}
+
+impl Default for ButtonConfig {
+    fn default() -> Self {
+        ButtonConfig {
+            long_press: Duration::from_millis(800),
+            double_click: Duration::from_millis(250),
+            active_high: false,
+        }
+    }
+}
+
+impl ButtonConfig {
+    /// Sets ``long=<duration>``, ``double=<duration>`` or ``active=high|low``,
+    /// ``Ok(false)`` if the key is no button option
+    pub(crate) fn set(&mut self, key: &str, value: &str) -> Result<bool, ResourceError> {
+        match key {
+            "long" => {
+                self.long_press = time::parse_duration(value).ok_or(ResourceError::ParseError)?
+            }
+            "double" => {
+                self.double_click = time::parse_duration(value).ok_or(ResourceError::ParseError)?
+            }
+            "active" => {
+                self.active_high = match value {
+                    "high" => true,
+                    "low" => false,
+                    _ => return Err(ResourceError::ParseError),
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum State {
+    Idle,
+    /// ``second`` is set for the press after a click
+    Pressed {
+        since: Timestamp,
+        second: bool,
+    },
+    /// Held past the long press time
+    Held,
+    /// Released, a press within the double click time makes a double click
+    Released {
+        at: Timestamp,
+    },
+}
+
+/// The gesture recognition of one ``event:button/...`` subscriber,
+/// it is kept next to its [EventCursor]
+#[derive(Copy, Clone, Debug)]
+pub struct GestureState {
+    state: State,
+    /// The second gesture of a transition, e.g. the double click after a release
+    pending: Option<(Gesture, Timestamp)>,
+}
+
+impl Default for GestureState {
+    fn default() -> Self {
+        GestureState {
+            state: State::Idle,
+            pending: None,
+        }
+    }
+}
+
+impl GestureState {
+    /// When the current state times out without another edge
+    fn deadline(&self, config: &ButtonConfig) -> Option<Timestamp> {
+        match self.state {
+            State::Pressed { since, .. } => Some(since + config.long_press),
+            State::Released { at } => Some(at + config.double_click),
+            _ => None,
+        }
+    }
+    fn timeout(&mut self) -> Option<Gesture> {
+        match self.state {
+            State::Pressed { .. } => {
+                self.state = State::Held;
+                Some(Gesture::LongPress)
+            }
+            State::Released { .. } => {
+                self.state = State::Idle;
+                Some(Gesture::Click)
+            }
+            _ => None,
+        }
+    }
+    /// Repeated levels are bounces the pin did not filter, they are ignored
+    fn edge(&mut self, timestamp: Timestamp, pressed: bool) -> Option<Gesture> {
+        match (self.state, pressed) {
+            (State::Idle, true) => {
+                self.state = State::Pressed {
+                    since: timestamp,
+                    second: false,
+                };
+                Some(Gesture::Press)
+            }
+            (State::Released { .. }, true) => {
+                self.state = State::Pressed {
+                    since: timestamp,
+                    second: true,
+                };
+                Some(Gesture::Press)
+            }
+            (State::Pressed { second, .. }, false) => {
+                if second {
+                    self.state = State::Idle;
+                    self.pending = Some((Gesture::DoubleClick, timestamp));
+                } else {
+                    self.state = State::Released { at: timestamp };
+                }
+                Some(Gesture::Release)
+            }
+            (State::Held, false) => {
+                self.state = State::Idle;
+                Some(Gesture::Release)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The next gesture of the subscriber behind ``cursor`` and ``button``, or
+/// the time the subscriber has to look again if no edge comes in before
+pub(crate) fn next_gesture(
+    log: &EventLog,
+    cursor: &mut EventCursor,
+    button: &mut GestureState,
+    config: &ButtonConfig,
+    now: Timestamp,
+) -> Result<(Gesture, Timestamp), Option<Timestamp>> {
+    loop {
+        if let Some(gesture) = button.pending.take() {
+            break Ok(gesture);
+        }
+        // timeouts before the next edge come first
+        let mut ahead = *cursor;
+        let edge = log.next(&mut ahead);
+        let horizon = edge.map_or(now, |(timestamp, _)| timestamp);
+        if let Some(deadline) = button.deadline(config) {
+            if deadline <= horizon {
+                if let Some(gesture) = button.timeout() {
+                    break Ok((gesture, deadline));
+                }
+                continue;
+            }
+        }
+        match edge {
+            Some((timestamp, high)) => {
+                *cursor = ahead;
+                if let Some(gesture) = button.edge(timestamp, high == config.active_high) {
+                    break Ok((gesture, timestamp));
+                }
+            }
+            None => {
+                // keeps the subscription of the first read
+                *cursor = ahead;
+                break Err(button.deadline(config));
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Timestamp {
+        Timestamp::from_micros(ms * 1000)
+    }
+
+    /// Feeds the edges of an active low button (time in ms, pressed) and
+    /// returns the gestures recognized until ``end`` ms
+    fn gestures(edges: &[(u64, bool)], end: u64) -> Vec<(Gesture, u64)> {
+        let config = ButtonConfig::default();
+        let mut log = EventLog::new();
+        let mut cursor = EventCursor::default();
+        let mut button = GestureState::default();
+        // the first read subscribes
+        assert_eq!(
+            next_gesture(&log, &mut cursor, &mut button, &config, ms(0)),
+            Err(None)
+        );
+        for &(at, pressed) in edges {
+            log.record(ms(at), !pressed);
+        }
+        let mut gestures = Vec::new();
+        while let Ok((gesture, at)) = next_gesture(&log, &mut cursor, &mut button, &config, ms(end))
+        {
+            gestures.push((gesture, at.as_micros() / 1000));
+        }
+        gestures
+    }
+
+    #[test]
+    fn click_after_the_double_click_time() {
+        use Gesture::*;
+        assert_eq!(
+            gestures(&[(10, true), (60, false)], 1000),
+            [(Press, 10), (Release, 60), (Click, 310)]
+        );
+    }
+
+    #[test]
+    fn click_is_pending_until_the_double_click_time() {
+        let config = ButtonConfig::default();
+        let mut log = EventLog::new();
+        let mut cursor = EventCursor::default();
+        let mut button = GestureState::default();
+        let _ = next_gesture(&log, &mut cursor, &mut button, &config, ms(0));
+        log.record(ms(10), false);
+        log.record(ms(60), true);
+        let mut next = || next_gesture(&log, &mut cursor, &mut button, &config, ms(100));
+        assert_eq!(next(), Ok((Gesture::Press, ms(10))));
+        assert_eq!(next(), Ok((Gesture::Release, ms(60))));
+        assert_eq!(next(), Err(Some(ms(310))));
+    }
+
+    #[test]
+    fn double_click() {
+        use Gesture::*;
+        assert_eq!(
+            gestures(&[(10, true), (60, false), (200, true), (250, false)], 1000),
+            [
+                (Press, 10),
+                (Release, 60),
+                (Press, 200),
+                (Release, 250),
+                (DoubleClick, 250)
+            ]
+        );
+    }
+
+    #[test]
+    fn long_press() {
+        use Gesture::*;
+        assert_eq!(
+            gestures(&[(10, true), (1000, false)], 2000),
+            [(Press, 10), (LongPress, 810), (Release, 1000)]
+        );
+    }
+
+    #[test]
+    fn long_press_after_a_click() {
+        use Gesture::*;
+        assert_eq!(
+            gestures(&[(10, true), (60, false), (200, true), (1100, false)], 2000),
+            [
+                (Press, 10),
+                (Release, 60),
+                (Press, 200),
+                (LongPress, 1000),
+                (Release, 1100)
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_levels_are_ignored() {
+        use Gesture::*;
+        assert_eq!(
+            gestures(&[(10, true), (12, true), (60, false), (61, false)], 1000),
+            [(Press, 10), (Release, 60), (Click, 310)]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+//! The text format of the scheme values, see [Format](crate::schemes::Format).
+//!
+//! Resources read and write the binary values of their scheme, a
+//! [ResourceID](super::ResourceID) with ``fmt=text`` converts them here.
+use crate::io;
+use crate::schemes::Scheme;
+use crate::utilities::ByteWriter;
+use core::fmt::Write;
+
+/// Space for the binary value of every scheme
+pub(crate) const VALUE_CAPACITY: usize = 16;
+
+/// Writes the binary ``value`` of ``scheme`` as text into ``buf``
+pub(crate) fn encode_text(
+    scheme: Scheme,
+    value: &[u8],
+    buf: &mut [u8],
+) -> Result<usize, io::Error> {
+    let mut writer = ByteWriter::new(buf);
+    let written = match scheme {
+        Scheme::Digital => write!(writer, "{}", (value[0] != 0) as u8),
+        Scheme::Analog => write!(
+            writer,
+            "{}",
+            from_target_endianess!(usize, value).map_err(|_| io::Error::InvalidData)?
+        ),
+        Scheme::Percent => write!(
+            writer,
+            "{}",
+            from_target_endianess!(f32, value).map_err(|_| io::Error::InvalidData)?
+        ),
+        Scheme::Event => match value.split_first() {
+            Some((byte, [])) => write!(writer, "{}", byte),
+            Some((byte, timestamp)) => write!(
+                writer,
+                "{} {}",
+                byte,
+                from_target_endianess!(u64, timestamp).map_err(|_| io::Error::InvalidData)?
+            ),
+            None => return Ok(0),
+        },
+        Scheme::Sys | Scheme::Memory | Scheme::Bus => return Err(io::Error::InvalidInput),
+    };
+    written.map_err(|_| io::Error::InvalidInput)?;
+    Ok(writer.written())
+}
+
+/// Parses the text of a value of ``scheme`` into its binary value,
+/// returns the size of the value
+pub(crate) fn decode_text(
+    scheme: Scheme,
+    text: &[u8],
+    value: &mut [u8; VALUE_CAPACITY],
+) -> Result<usize, io::Error> {
+    let text = core::str::from_utf8(text)
+        .map_err(|_| io::Error::InvalidData)?
+        .trim();
+    let bytes = match scheme {
+        Scheme::Digital => match text {
+            "0" => copy_value(value, &[0]),
+            "1" => copy_value(value, &[1]),
+            _ => return Err(io::Error::InvalidData),
+        },
+        Scheme::Analog => {
+            let analog: usize = text.parse().map_err(|_| io::Error::InvalidData)?;
+            copy_value(value, &to_target_endianess!(analog))
+        }
+        Scheme::Percent => {
+            let percent: f32 = text.parse().map_err(|_| io::Error::InvalidData)?;
+            copy_value(value, &to_target_endianess!(percent))
+        }
+        Scheme::Event | Scheme::Sys | Scheme::Memory | Scheme::Bus => {
+            return Err(io::Error::InvalidInput)
+        }
+    };
+    Ok(bytes)
+}
+
+/// Writes a binary value for a reader, the buffer has to fit it
+pub(crate) fn write_value(buf: &mut [u8], bytes: &[u8]) -> Result<usize, io::Error> {
+    if buf.len() < bytes.len() {
+        return Err(io::Error::InvalidInput);
+    }
+    buf[..bytes.len()].copy_from_slice(bytes);
+    Ok(bytes.len())
+}
+
+fn copy_value(value: &mut [u8; VALUE_CAPACITY], bytes: &[u8]) -> usize {
+    value[..bytes.len()].copy_from_slice(bytes);
+    bytes.len()
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use super::*;
+
+    fn encode(scheme: Scheme, value: &[u8]) -> Result<String, io::Error> {
+        let mut buf = [0; 32];
+        let len = encode_text(scheme, value, &mut buf)?;
+        Ok(core::str::from_utf8(&buf[..len]).unwrap().into())
+    }
+
+    fn decode(scheme: Scheme, text: &str) -> Result<Vec<u8>, io::Error> {
+        let mut value = [0; VALUE_CAPACITY];
+        let len = decode_text(scheme, text.as_bytes(), &mut value)?;
+        Ok(value[..len].to_vec())
+    }
+
+    #[test]
+    fn values_are_encoded_as_text() {
+        assert_eq!(encode(Scheme::Digital, &[1]).unwrap(), "1");
+        assert_eq!(encode(Scheme::Digital, &[0]).unwrap(), "0");
+        assert_eq!(
+            encode(Scheme::Analog, &to_target_endianess!(4095usize)).unwrap(),
+            "4095"
+        );
+        assert_eq!(
+            encode(Scheme::Percent, &to_target_endianess!(0.25f32)).unwrap(),
+            "0.25"
+        );
+        let mut event = [2; 9];
+        event[1..].copy_from_slice(&to_target_endianess!(1500u64));
+        assert_eq!(encode(Scheme::Event, &event).unwrap(), "2 1500");
+        assert_eq!(encode(Scheme::Event, &[2]).unwrap(), "2");
+        assert_eq!(encode(Scheme::Event, &[]).unwrap(), "");
+        assert_eq!(encode(Scheme::Bus, &[1]), Err(io::Error::InvalidInput));
+    }
+
+    #[test]
+    fn encoding_fails_if_the_text_does_not_fit() {
+        let mut buf = [0; 2];
+        assert_eq!(
+            encode_text(Scheme::Analog, &to_target_endianess!(4095usize), &mut buf),
+            Err(io::Error::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn text_is_decoded_into_values() {
+        assert_eq!(decode(Scheme::Digital, "1\n").unwrap(), [1]);
+        assert_eq!(decode(Scheme::Digital, " 0").unwrap(), [0]);
+        assert_eq!(decode(Scheme::Digital, "2"), Err(io::Error::InvalidData));
+        assert_eq!(
+            decode(Scheme::Analog, "4095").unwrap(),
+            to_target_endianess!(4095usize)
+        );
+        assert_eq!(decode(Scheme::Analog, "-1"), Err(io::Error::InvalidData));
+        assert_eq!(
+            decode(Scheme::Percent, "0.25").unwrap(),
+            to_target_endianess!(0.25f32)
+        );
+        assert_eq!(decode(Scheme::Percent, "half"), Err(io::Error::InvalidData));
+        assert_eq!(decode(Scheme::Event, "1"), Err(io::Error::InvalidInput));
+        assert_eq!(
+            decode_text(Scheme::Digital, &[0xff], &mut [0; VALUE_CAPACITY]),
+            Err(io::Error::InvalidData)
+        );
+    }
+
+    #[test]
+    fn values_survive_a_round_trip() {
+        for (scheme, text) in [
+            (Scheme::Digital, "1"),
+            (Scheme::Analog, "123"),
+            (Scheme::Percent, "0.5"),
+        ] {
+            assert_eq!(
+                encode(scheme, &decode(scheme, text).unwrap()).unwrap(),
+                text
+            );
+        }
+    }
+}
@@ -1,10 +1,13 @@
-use super::{path::RawPath, Resource, ResourceError, ResourceMode};
-use crate::{device::ExtiEvent, events::Event, schemes::Scheme, utilities::ByteWriter, Runtime};
+use super::button::{self, GestureState};
+use super::{codec::write_value, path::RawPath, Resource, ResourceError, ResourceMode};
+use crate::events::{self, Event, EventCursor, EventLog, TimedEvent};
+use crate::time::Timestamp;
+use crate::{device::ExtiEvent, schemes::Scheme, Runtime};
 use crate::{
     device::{Channel, Port},
     io,
 };
-use core::fmt::Write;
+use core::time::Duration;
 use core::{cmp::Ordering, task::Context, task::Poll};
 use embedded_hal::digital::v2;
 use io::SeekFrom;
@@ -69,8 +72,11 @@ pub enum TriggerEdge {
 pub struct InputPin<HalPin: 'static> {
     id: Pin,
     resource: HalPin,
-    /// The amount of unhandles events
-    events: u8,
+    /// The recent edges for the subscribed tasks
+    events: EventLog,
+    /// Edges closer than this to the last recorded edge are bounces
+    debounce: Duration,
+    last_edge: Option<Timestamp>,
 }
 pub struct OutputPin<HalPin: 'static> {
     id: Pin,
@@ -84,7 +90,7 @@ where
 {
     fn poll_read(
         &mut self,
-        context: &mut Context,
+        _context: &mut Context,
         scheme: Scheme,
         mode: ResourceMode,
         buf: &mut [u8],
@@ -94,10 +100,7 @@ where
             match scheme {
                 // read the pin normaly
                 Scheme::Digital => match self.resource.is_high() {
-                    Ok(res) => {
-                        buf[0] = res as u8;
-                        Poll::Ready(Ok(1))
-                    }
+                    Ok(res) => Poll::Ready(write_value(buf, &[res as u8])),
                     Err(e) => {
                         log::error!("{}", e);
                         Poll::Ready(Err(io::Error::Other))
@@ -105,36 +108,14 @@ where
                 },
                 Scheme::Percent => match self.resource.is_high() {
                     Ok(res) => {
-                        let res = {
-                            if res {
-                                1.0f32
-                            } else {
-                                0.0f32
-                            }
-                        };
-                        let mut buffer = ByteWriter::new(buf);
-                        write!(buffer, "{}", res).map_err(|_| io::Error::InvalidInput)?;
-                        Poll::Ready(Ok(buffer.written()))
+                        let res = if res { 1.0f32 } else { 0.0f32 };
+                        Poll::Ready(write_value(buf, &to_target_endianess!(res)))
                     }
                     Err(e) => {
                         log::error!("{}", e);
                         Poll::Ready(Err(io::Error::Other))
                     }
                 },
-                // handle a gpio ecent
-                Scheme::Event => {
-                    if self.events > 0 {
-                        self.events -= 1;
-                        buf[0] = 0 as u8;
-                        Poll::Ready(Ok(1))
-                    } else {
-                        Runtime::get().register_waker(
-                            &Event::ExternalInterrupt(ExtiEvent::Gpio(self.id)),
-                            context.waker(),
-                        );
-                        Poll::Pending
-                    }
-                }
                 _ => Poll::Ready(Err(io::Error::InvalidInput)),
             }
         } else {
@@ -178,8 +159,51 @@ where
     fn path(&self) -> RawPath {
         RawPath::Gpio(self.id)
     }
-    fn handle_event(&mut self) {
-        self.events += 1;
+    /// Every subscribed task reads every edge: the event byte, followed by
+    /// the timestamp in microseconds if the buffer has at least 9 bytes.
+    /// In button mode the event byte is a [Gesture](super::Gesture).
+    fn poll_event(
+        &mut self,
+        context: &mut Context<'_>,
+        mode: ResourceMode,
+        cursor: &mut EventCursor,
+        gestures: &mut GestureState,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let waker = context.waker();
+        match mode {
+            ResourceMode::Default => {
+                if let Some((timestamp, _high)) = self.events.next(cursor) {
+                    return Poll::Ready(Ok(events::write_event(0, timestamp, buf)));
+                }
+            }
+            ResourceMode::Button(config) => {
+                let now = Timestamp::now();
+                match button::next_gesture(&self.events, cursor, gestures, &config, now) {
+                    Ok((gesture, timestamp)) => {
+                        return Poll::Ready(Ok(events::write_event(gesture as u8, timestamp, buf)))
+                    }
+                    Err(Some(deadline)) => Runtime::register_timer(deadline.next_instant(), waker),
+                    Err(None) => {}
+                }
+            }
+            _ => return Poll::Ready(Err(io::Error::InvalidInput)),
+        }
+        Runtime::register_waker(&Event::ExternalInterrupt(ExtiEvent::Gpio(self.id)), waker);
+        Poll::Pending
+    }
+    /// Bounces are dropped here, so they wake no task.
+    /// The level was sampled by the interrupt handler right after the edge,
+    /// it tells rising from falling edges.
+    fn handle_event(&mut self, event: &TimedEvent) -> bool {
+        if let Some(last) = self.last_edge {
+            if event.timestamp.duration_since(last) < self.debounce {
+                return false;
+            }
+        }
+        self.last_edge = Some(event.timestamp);
+        self.events.record(event.timestamp, event.high);
+        true
     }
 }
 impl<HalPin, Error> InputPin<HalPin>
@@ -191,9 +215,16 @@ where
         InputPin {
             id: pin,
             resource: hal_pin,
-            events: 0,
+            events: EventLog::new(),
+            debounce: Duration::from_micros(0),
+            last_edge: None,
         }
     }
+    /// Edges within ``debounce`` after a recorded edge are dropped
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
     pub fn get_pin(&self) -> Pin {
         self.id
     }
@@ -294,7 +325,9 @@ where
     fn path(&self) -> RawPath {
         RawPath::Gpio(self.id)
     }
-    fn handle_event(&mut self) {}
+    fn handle_event(&mut self, _event: &TimedEvent) -> bool {
+        true
+    }
 }
 impl<HalPin, Error> OutputPin<HalPin>
 where
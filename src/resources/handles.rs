@@ -0,0 +1,253 @@
+//! Typed handles of a [ResourceID](super::ResourceID), one per scheme.
+//!
+//! The handles encode and decode the binary values of their scheme
+//! (see [Format](crate::schemes::Format)), e.g.
+//! ```ignore
+//! let mut led = Runtime::get().get_resource("digital:gpio/pc13")?.into_digital()?;
+//! led.set_high().await?;
+//! let mut pwm = Runtime::get().get_resource("percent:pwm/pa1")?.into_percent()?;
+//! pwm.set(0.5).await?;
+//! let mut button = Runtime::get().get_resource("event:gpio/pa0")?.into_event()?;
+//! let timestamp = button.next().await?;
+//! ```
+//! Memory and bus handles move raw bytes, they are [AsyncRead] and [AsyncWrite].
+use super::{Gesture, ResourceError, ResourceID};
+use crate::events::EVENT_SIZE;
+use crate::io::{self, AsyncRead, AsyncReadExt, AsyncSeek, AsyncWrite, AsyncWriteExt};
+use crate::time::Timestamp;
+use core::pin::Pin;
+use core::str::FromStr;
+use core::task::{Context, Poll};
+
+/// Space for the text of a single sys value
+const SYS_VALUE_LEN: usize = 24;
+
+/// Bytes of a ``memory:`` resource
+pub struct Memory {
+    id: ResourceID,
+}
+
+/// Bytes of a ``bus:`` resource, e.g. a serial port
+pub struct Bus {
+    id: ResourceID,
+}
+
+/// An ``analog:`` value, e.g. the raw duty of a pwm pin
+pub struct Analog {
+    id: ResourceID,
+}
+
+/// A ``digital:`` pin
+pub struct Digital {
+    id: ResourceID,
+}
+
+/// The events of an ``event:`` resource, the handle is a subscriber of its own
+pub struct Event {
+    id: ResourceID,
+}
+
+/// A ``sys:`` value of the runtime, they are text
+pub struct Sys {
+    id: ResourceID,
+}
+
+/// A ``percent:`` value between 0.0 and 1.0
+pub struct Percent {
+    id: ResourceID,
+}
+
+impl Analog {
+    pub(crate) fn new(id: ResourceID) -> Self {
+        Analog { id }
+    }
+    pub async fn get(&mut self) -> Result<usize, io::Error> {
+        let mut value = [0; core::mem::size_of::<usize>()];
+        self.id.read_exact(&mut value).await?;
+        from_target_endianess!(usize, &value[..]).map_err(|_| io::Error::InvalidData)
+    }
+    pub async fn set(&mut self, value: usize) -> Result<(), io::Error> {
+        self.id.write_all(&to_target_endianess!(value)).await
+    }
+    pub fn id(&self) -> ResourceID {
+        self.id
+    }
+}
+
+impl Digital {
+    pub(crate) fn new(id: ResourceID) -> Self {
+        Digital { id }
+    }
+    pub async fn is_high(&mut self) -> Result<bool, io::Error> {
+        let mut level = [0];
+        self.id.read_exact(&mut level).await?;
+        Ok(level[0] != 0)
+    }
+    pub async fn is_low(&mut self) -> Result<bool, io::Error> {
+        Ok(!self.is_high().await?)
+    }
+    pub async fn set(&mut self, high: bool) -> Result<(), io::Error> {
+        self.id.write_all(&[high as u8]).await
+    }
+    pub async fn set_high(&mut self) -> Result<(), io::Error> {
+        self.set(true).await
+    }
+    pub async fn set_low(&mut self) -> Result<(), io::Error> {
+        self.set(false).await
+    }
+    pub fn id(&self) -> ResourceID {
+        self.id
+    }
+}
+
+impl Event {
+    pub(crate) fn new(id: ResourceID) -> Self {
+        Event { id }
+    }
+    /// Waits for the next event and returns the time it happened
+    pub async fn next(&mut self) -> Result<Timestamp, io::Error> {
+        self.next_byte().await.map(|(_byte, timestamp)| timestamp)
+    }
+    /// The next gesture of an ``event:button/<pin>`` resource
+    pub async fn next_gesture(&mut self) -> Result<(Gesture, Timestamp), io::Error> {
+        let (byte, timestamp) = self.next_byte().await?;
+        let gesture = Gesture::from_byte(byte).ok_or(io::Error::InvalidData)?;
+        Ok((gesture, timestamp))
+    }
+    /// Raises a user event, see [ResourceID::raise]
+    pub fn raise(&self) -> Result<(), ResourceError> {
+        self.id.raise()
+    }
+    async fn next_byte(&mut self) -> Result<(u8, Timestamp), io::Error> {
+        let mut buf = [0; EVENT_SIZE];
+        if self.id.read(&mut buf).await? != buf.len() {
+            return Err(io::Error::UnexpectedEof);
+        }
+        let micros = from_target_endianess!(u64, &buf[1..]).map_err(|_| io::Error::InvalidData)?;
+        Ok((buf[0], Timestamp::from_micros(micros)))
+    }
+    pub fn id(&self) -> ResourceID {
+        self.id
+    }
+}
+
+impl Sys {
+    pub(crate) fn new(id: ResourceID) -> Self {
+        Sys { id }
+    }
+    /// Parses a single value, e.g. of ``sys:heap`` or ``sys:lost_events``
+    pub async fn get<T: FromStr>(&mut self) -> Result<T, io::Error> {
+        let mut buf = [0; SYS_VALUE_LEN];
+        self.read_str(&mut buf)
+            .await?
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::InvalidData)
+    }
+    /// Reads the whole text, e.g. the table of ``sys:tasks``
+    pub async fn read_str<'b>(&mut self, buf: &'b mut [u8]) -> Result<&'b str, io::Error> {
+        let len = self.id.read(buf).await?;
+        core::str::from_utf8(&buf[..len]).map_err(|_| io::Error::InvalidData)
+    }
+    pub fn id(&self) -> ResourceID {
+        self.id
+    }
+}
+
+impl Percent {
+    pub(crate) fn new(id: ResourceID) -> Self {
+        Percent { id }
+    }
+    pub async fn get(&mut self) -> Result<f32, io::Error> {
+        let mut value = [0; core::mem::size_of::<f32>()];
+        self.id.read_exact(&mut value).await?;
+        from_target_endianess!(f32, &value[..]).map_err(|_| io::Error::InvalidData)
+    }
+    /// Values outside of 0.0 to 1.0 are rejected with ``InvalidData``
+    pub async fn set(&mut self, value: f32) -> Result<(), io::Error> {
+        if !(0.0..=1.0).contains(&value) {
+            return Err(io::Error::InvalidData);
+        }
+        self.id.write_all(&to_target_endianess!(value)).await
+    }
+    pub fn id(&self) -> ResourceID {
+        self.id
+    }
+}
+
+impl Memory {
+    pub(crate) fn new(id: ResourceID) -> Self {
+        Memory { id }
+    }
+    pub fn id(&self) -> ResourceID {
+        self.id
+    }
+}
+
+impl Bus {
+    pub(crate) fn new(id: ResourceID) -> Self {
+        Bus { id }
+    }
+    pub fn id(&self) -> ResourceID {
+        self.id
+    }
+}
+
+impl AsyncRead for Memory {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().id).poll_read(cx, buf)
+    }
+}
+impl AsyncWrite for Memory {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().id).poll_write(cx, buf)
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().id).poll_flush(cx)
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().id).poll_close(cx)
+    }
+}
+impl AsyncSeek for Memory {
+    fn poll_seek(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        pos: io::SeekFrom,
+    ) -> Poll<Result<u64, io::Error>> {
+        Pin::new(&mut self.get_mut().id).poll_seek(cx, pos)
+    }
+}
+
+impl AsyncRead for Bus {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().id).poll_read(cx, buf)
+    }
+}
+impl AsyncWrite for Bus {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.get_mut().id).poll_write(cx, buf)
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().id).poll_flush(cx)
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.get_mut().id).poll_close(cx)
+    }
+}
@@ -1,20 +1,29 @@
+pub mod button;
+mod codec;
 pub mod gpio;
+pub mod handles;
 pub mod path;
 pub mod pwm;
 pub mod sys;
+pub mod user;
 
+use crate::events::{EventCursor, TimedEvent};
 use crate::Runtime;
 use crate::{
     io::{self, AsyncRead, AsyncSeek, AsyncWrite},
-    schemes::Scheme,
+    schemes::{Format, Scheme},
 };
+pub use button::Gesture;
+use button::GestureState;
 use core::str::Split;
 use core::task::{Context, Poll};
 pub use gpio::{InputPin, OutputPin, Pin};
+pub use handles::{Analog, Bus, Digital, Event, Memory, Percent, Sys};
 use nom_uri::Uri;
 use path::{IndexedPath, RawPath, ResourceMode};
 pub use pwm::PWMPin;
 pub use sys::SysResource;
+pub use user::UserEvent;
 
 #[non_exhaustive]
 #[derive(Debug)]
@@ -33,7 +42,8 @@ pub enum ResourceError {
     Unresolvable,
     ParseError,
 }
-#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+/// Each copy of an id is a subscriber of its own to the events of the resource
+#[derive(Copy, Clone, Debug)]
 pub struct ResourceID {
     /// Determines the data format which the resource accepts and returns
     scheme: Scheme,
@@ -41,6 +51,30 @@ pub struct ResourceID {
     index: IndexedPath,
     /// Resources function diferently in different modes
     mode: ResourceMode,
+    /// Encoding of the values, resources read and write binary values.
+    /// ``None`` until ``fmt`` selects one, see [Scheme::default_format]
+    format: Option<Format>,
+    /// Events this handle has read, ``event:`` reads only
+    cursor: EventCursor,
+    /// Gesture recognition of ``event:button/...`` reads, unused otherwise
+    gestures: GestureState,
+}
+impl PartialEq for ResourceID {
+    fn eq(&self, other: &Self) -> bool {
+        self.scheme == other.scheme
+            && self.index == other.index
+            && self.mode == other.mode
+            && self.format() == other.format()
+    }
+}
+impl Eq for ResourceID {}
+impl core::hash::Hash for ResourceID {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.scheme.hash(state);
+        self.index.hash(state);
+        self.mode.hash(state);
+        self.format().hash(state);
+    }
 }
 
 pub(crate) struct Resources {
@@ -51,6 +85,7 @@ pub(crate) struct Resources {
     pub(crate) channels: &'static mut [&'static mut dyn Resource],
     pub(crate) serials: &'static mut [&'static mut dyn Resource],
     pub(crate) timers: &'static mut [&'static mut dyn Resource],
+    pub(crate) user_events: &'static mut [UserEvent],
     // pub(crate) generic_resources: BTreeMap<u8, Box<dyn Resource>>,
 }
 
@@ -63,6 +98,7 @@ impl Resources {
         channels: &'static mut [&'static mut dyn Resource],
         serials: &'static mut [&'static mut dyn Resource],
         timers: &'static mut [&'static mut dyn Resource],
+        user_events: &'static mut [UserEvent],
     ) -> Self {
         Self {
             sys,
@@ -72,6 +108,7 @@ impl Resources {
             channels,
             serials,
             timers,
+            user_events,
             // generic_resources: BTreeMap::new(),
         }
     }
@@ -84,6 +121,7 @@ impl Resources {
             IndexedPath::ADCPin(index) => *self.channels.get_mut(index as usize).unwrap(),
             IndexedPath::Serial(index) => *self.serials.get_mut(index as usize).unwrap(),
             IndexedPath::Timer(index) => *self.timers.get_mut(index as usize).unwrap(),
+            IndexedPath::User(index) => self.user_events.get_mut(index as usize).unwrap(),
             // IndexedPath::Generic(key) => self
             //     .generic_resources
             //     .get_mut(&key)
@@ -94,13 +132,19 @@ impl Resources {
     pub fn get_resource(&'static mut self, uri: &str) -> Result<ResourceID, ResourceError> {
         use core::convert::TryFrom;
         use core::str::FromStr;
-        let parsed_uri = Uri::try_from(uri).or(Err(ResourceError::UriParseError))?;
+        // the query configures the resource id and is not part of the resource path
+        let mut uri = uri.splitn(2, '?');
+        let parsed_uri =
+            Uri::try_from(uri.next().unwrap_or("")).or(Err(ResourceError::UriParseError))?;
+        let query = uri.next();
         let (index, mode) = RawPath::from_str(parsed_uri.path())?.resolve()?;
-        Ok(ResourceID::new(
-            Scheme::from_str(parsed_uri.scheme()).map_err(|_| ResourceError::UriParseError)?,
-            index,
-            mode,
-        ))
+        let scheme =
+            Scheme::from_str(parsed_uri.scheme()).map_err(|_| ResourceError::UriParseError)?;
+        let mut id = ResourceID::new(scheme, index, mode);
+        if let Some(query) = query {
+            id.apply_query(query)?;
+        }
+        Ok(id)
     }
     fn search_resource_array(
         &self,
@@ -120,6 +164,19 @@ impl Resources {
         let index = resources.search_resource_array(&path, resources.input_pins)?;
         Ok(resources.input_pins[index as usize])
     }
+    pub(crate) fn search_user_event(&self, name: &str) -> Result<u8, ResourceError> {
+        self.user_events
+            .iter()
+            .position(|event| event.name() == name)
+            .map(|index| index as u8)
+            .ok_or(ResourceError::NotFound)
+    }
+    pub(crate) fn get_user_event(index: u8) -> Result<&'static mut UserEvent, ResourceError> {
+        Runtime::get_resources()
+            .user_events
+            .get_mut(index as usize)
+            .ok_or(ResourceError::NotFound)
+    }
     // fn search_virtual_resources(&self, path: &RawPath) -> Result<u8, ResourceError> {
     //     self.generic_resources
     //         .iter()
@@ -135,35 +192,6 @@ impl Resources {
     // }
 }
 
-pub struct Memory {
-    index: IndexedPath,
-    mode_suffix: ResourceMode,
-}
-pub struct Bus {
-    index: IndexedPath,
-    mode: ResourceMode,
-}
-pub struct Analog {
-    index: IndexedPath,
-    mode: ResourceMode,
-}
-pub struct Digital {
-    index: IndexedPath,
-    mode: ResourceMode,
-}
-pub struct Event {
-    index: IndexedPath,
-    mode: ResourceMode,
-}
-pub struct Sys {
-    index: IndexedPath,
-    mode: ResourceMode,
-}
-pub struct Percent {
-    index: IndexedPath,
-    mode: ResourceMode,
-}
-
 /// Inspired by the async io traits of the futures trait
 pub trait Resource {
     fn poll_read(
@@ -199,7 +227,23 @@ pub trait Resource {
         mode: ResourceMode,
         pos: io::SeekFrom,
     ) -> Poll<Result<u64, io::Error>>;
-    fn handle_event(&mut self);
+    /// Reads the next event the subscriber behind ``cursor`` did not see yet,
+    /// it is called instead of poll_read for the ``event`` scheme.
+    /// ``gestures`` is only used in button mode.
+    fn poll_event(
+        &mut self,
+        _cx: &mut Context<'_>,
+        _mode: ResourceMode,
+        _cursor: &mut EventCursor,
+        _gestures: &mut GestureState,
+        _buf: &mut [u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Poll::Ready(Err(io::Error::InvalidInput))
+    }
+    /// Called by the executor for every event of the resource.
+    /// Returns false if the resource dropped the event, e.g. a bounce,
+    /// the subscribers of the event are not woken then.
+    fn handle_event(&mut self, event: &TimedEvent) -> bool;
     fn path(&self) -> RawPath;
 }
 
@@ -209,31 +253,111 @@ impl ResourceID {
             scheme,
             index,
             mode,
+            format: None,
+            cursor: EventCursor::default(),
+            gestures: GestureState::default(),
         }
     }
     pub(crate) fn get_index(&self) -> IndexedPath {
         self.index
     }
+    /// Applies the ``key=value`` pairs of an uri query, separated by ``&``.
+    /// ``fmt=bin|text`` selects the [Format] of every scheme that supports it.
+    fn apply_query(&mut self, query: &str) -> Result<(), ResourceError> {
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let mut pair = pair.splitn(2, '=');
+            let key = pair.next().unwrap_or("");
+            let value = pair.next().ok_or(ResourceError::ParseError)?;
+            if key == "fmt" {
+                self.set_format(value)?;
+                continue;
+            }
+            let known = match (self.scheme, &mut self.mode) {
+                (Scheme::Event, ResourceMode::Button(config)) => {
+                    self.cursor.filter.set(key, value)? || config.set(key, value)?
+                }
+                (Scheme::Event, _) => self.cursor.filter.set(key, value)?,
+                _ => false,
+            };
+            if !known {
+                return Err(ResourceError::ParseError);
+            }
+        }
+        Ok(())
+    }
+    fn set_format(&mut self, format: &str) -> Result<(), ResourceError> {
+        use core::str::FromStr;
+        let format = Format::from_str(format).map_err(|_| ResourceError::ParseError)?;
+        if !self.scheme.supports(format) {
+            return Err(ResourceError::ConfigurationError);
+        }
+        self.format = Some(format);
+        Ok(())
+    }
+    #[inline]
+    fn format(&self) -> Format {
+        self.format.unwrap_or_else(|| self.scheme.default_format())
+    }
+    /// Raises the user event behind the id like a write does,
+    /// but it does not access the resource, so interrupt handlers can use it
+    pub fn raise(&self) -> Result<(), ResourceError> {
+        match (self.scheme, self.index) {
+            (Scheme::Event, IndexedPath::User(index)) => {
+                user::raise(index);
+                Ok(())
+            }
+            _ => Err(ResourceError::NonWritingResource),
+        }
+    }
+    /// Fails with ``ConversionError`` if the id is not a ``memory:`` id,
+    /// the other conversions check their scheme the same way.
+    /// The handles encode the values themselves, they use the binary values
+    /// (text for ``sys:``) and fail the same way if ``fmt`` selected the
+    /// other format.
     pub fn into_memory(self) -> Result<Memory, ResourceError> {
-        unimplemented!()
+        self.expect_scheme(Scheme::Memory).map(Memory::new)
     }
     pub fn into_bus(self) -> Result<Bus, ResourceError> {
-        unimplemented!()
+        self.expect_scheme(Scheme::Bus).map(Bus::new)
     }
     pub fn into_analog(self) -> Result<Analog, ResourceError> {
-        unimplemented!()
+        self.expect_scheme(Scheme::Analog).map(Analog::new)
     }
     pub fn into_digital(self) -> Result<Digital, ResourceError> {
-        unimplemented!()
+        self.expect_scheme(Scheme::Digital).map(Digital::new)
     }
     pub fn into_event(self) -> Result<Event, ResourceError> {
-        unimplemented!()
+        self.expect_scheme(Scheme::Event).map(Event::new)
     }
     pub fn into_sys(self) -> Result<Sys, ResourceError> {
-        unimplemented!()
+        self.expect_scheme(Scheme::Sys).map(Sys::new)
     }
     pub fn into_percent(self) -> Result<Percent, ResourceError> {
-        unimplemented!()
+        self.expect_scheme(Scheme::Percent).map(Percent::new)
+    }
+    fn expect_scheme(mut self, scheme: Scheme) -> Result<Self, ResourceError> {
+        let format = match scheme {
+            Scheme::Sys => Format::Text,
+            _ => Format::Binary,
+        };
+        if self.scheme != scheme || self.format.map_or(false, |selected| selected != format) {
+            return Err(ResourceError::ConversionError);
+        }
+        self.format = Some(format);
+        Ok(self)
+    }
+    /// Reads the binary value, or the event of the cursor
+    fn poll_read_value(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        let resource = Runtime::get_resources().get_resource_object(self);
+        if self.scheme == Scheme::Event {
+            resource.poll_event(cx, self.mode, &mut self.cursor, &mut self.gestures, buf)
+        } else {
+            resource.poll_read(cx, self.scheme, self.mode, buf)
+        }
     }
     fn parse_mode(mode: Split<char>) -> ResourceMode {
         unimplemented!()
@@ -241,25 +365,43 @@ impl ResourceID {
 }
 impl Unpin for ResourceID {}
 impl AsyncRead for ResourceID {
+    /// Reads a binary value of the resource and encodes it as text
+    /// if the id has the text format
     fn poll_read(
         self: core::pin::Pin<&mut ResourceID>,
         cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<Result<usize, io::Error>> {
-        Runtime::get_resources()
-            .get_resource_object(&*self)
-            .poll_read(cx, self.scheme, self.mode, buf)
+        let this = self.get_mut();
+        let size = match (this.format(), this.scheme.value_size()) {
+            (Format::Text, Some(size)) => size,
+            _ => return this.poll_read_value(cx, buf),
+        };
+        let mut value = [0; codec::VALUE_CAPACITY];
+        match this.poll_read_value(cx, &mut value[..size]) {
+            Poll::Ready(Ok(len)) => {
+                Poll::Ready(codec::encode_text(this.scheme, &value[..len], buf))
+            }
+            poll => poll,
+        }
     }
 }
 impl AsyncWrite for ResourceID {
+    /// Text is decoded into one binary value, events are raised by any bytes
     fn poll_write(
         self: core::pin::Pin<&mut ResourceID>,
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<Result<usize, io::Error>> {
-        Runtime::get_resources()
-            .get_resource_object(&*self)
-            .poll_write(cx, self.scheme, self.mode, buf)
+        let resource = Runtime::get_resources().get_resource_object(&*self);
+        if self.format() == Format::Binary || self.scheme == Scheme::Event {
+            return resource.poll_write(cx, self.scheme, self.mode, buf);
+        }
+        let mut value = [0; codec::VALUE_CAPACITY];
+        let len = codec::decode_text(self.scheme, buf, &mut value)?;
+        resource
+            .poll_write(cx, self.scheme, self.mode, &value[..len])
+            .map_ok(|_| buf.len())
     }
     fn poll_flush(
         self: core::pin::Pin<&mut ResourceID>,
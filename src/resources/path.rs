@@ -1,15 +1,19 @@
 use crate::Runtime;
 
-use super::{gpio::Pin, pwm::PWMMode, sys::SysPaths, ResourceError};
+use super::{button::ButtonConfig, gpio::Pin, pwm::PWMMode, sys::SysPaths, ResourceError};
 
 #[derive(Copy, Clone, Eq, Debug, Hash)]
 pub enum RawPath {
     Sys(SysPaths),
     Gpio(Pin),
+    /// Gestures of a button on an input pin
+    Button(Pin),
     PWM(Pin, PWMMode),
     ADCPin(()),
     Serial(()),
     Timer(()),
+    /// Index of a [UserEvent](super::UserEvent)
+    User(u8),
     Generic(()),
 }
 
@@ -30,6 +34,13 @@ impl PartialEq for RawPath {
                     }
                 }
             }
+            RawPath::Button(pin) => {
+                if let RawPath::Button(o_pin) = other {
+                    if pin == o_pin {
+                        return true;
+                    }
+                }
+            }
             RawPath::PWM(pin, _) => {
                 if let RawPath::PWM(o_pin, _) = other {
                     if pin == o_pin {
@@ -58,6 +69,13 @@ impl PartialEq for RawPath {
                     }
                 }
             }
+            RawPath::User(index) => {
+                if let RawPath::User(o_index) = other {
+                    if index == o_index {
+                        return true;
+                    }
+                }
+            }
             RawPath::Generic(index) => {
                 if let RawPath::Generic(o_index) = other {
                     if index == o_index {
@@ -79,12 +97,14 @@ pub(crate) enum IndexedPath {
     ADCPin(u8),
     Serial(u8),
     Timer(u8),
+    User(u8),
     // Generic(u8),
 }
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
 pub enum ResourceMode {
     Default,
     PWM(PWMMode),
+    Button(ButtonConfig),
 }
 
 impl RawPath {
@@ -95,6 +115,9 @@ impl RawPath {
             Some("gpio") => Ok(RawPath::Gpio(Pin::from_str({
                 segments.next().ok_or(ResourceError::ConversionError)?
             })?)),
+            Some("button") => Ok(RawPath::Button(Pin::from_str(
+                segments.next().ok_or(ResourceError::ConversionError)?,
+            )?)),
             Some("pwm") => Ok(RawPath::PWM(
                 Pin::from_str(segments.next().ok_or(ResourceError::ConversionError)?)?,
                 PWMMode::from_str(segments.next().unwrap_or(""))?,
@@ -102,6 +125,9 @@ impl RawPath {
             Some("sys") => Ok(RawPath::Sys(SysPaths::from_str(
                 segments.next().ok_or(ResourceError::ConversionError)?,
             )?)),
+            Some("user") => Ok(RawPath::User(Runtime::get_resources().search_user_event(
+                segments.next().ok_or(ResourceError::ConversionError)?,
+            )?)),
             _ => Err(ResourceError::NotFound),
         }
     }
@@ -124,6 +150,12 @@ impl RawPath {
                     ))
                 }
             }
+            RawPath::Button(pin) => Ok((
+                IndexedPath::InputGpio(
+                    resources.search_resource_array(&RawPath::Gpio(pin), resources.input_pins)?,
+                ),
+                ResourceMode::Button(ButtonConfig::default()),
+            )),
             RawPath::PWM(_pin, mode) => Ok((
                 IndexedPath::PWM(resources.search_resource_array(&self, resources.pwm)?),
                 ResourceMode::PWM(mode),
@@ -140,6 +172,7 @@ impl RawPath {
                 IndexedPath::Timer(resources.search_resource_array(&self, resources.timers)?),
                 ResourceMode::Default,
             )),
+            RawPath::User(index) => Ok((IndexedPath::User(index), ResourceMode::Default)),
             RawPath::Generic(_) => {
                 // Scheme::Event => self
                 //     .search_virtual_resources(&parsed_uri)
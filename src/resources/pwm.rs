@@ -1,8 +1,8 @@
-use super::{gpio::Pin, path::RawPath, Resource, ResourceError, ResourceMode};
-use crate::{io, schemes::Scheme, utilities::ByteWriter};
+use super::{codec::write_value, gpio::Pin, path::RawPath, Resource, ResourceError, ResourceMode};
+use crate::{events::TimedEvent, io, schemes::Scheme};
 use core::{
     convert::{TryFrom, TryInto},
-    fmt::{Debug, Write},
+    fmt::Debug,
     ops::Mul,
     task::{Context, Poll},
 };
@@ -42,25 +42,16 @@ where
     ) -> Poll<Result<usize, io::Error>> {
         if let ResourceMode::PWM(mode) = mode {
             let duty: usize = self.resource.get_duty().into();
-            let mut buffer = ByteWriter::new(buf);
-            match mode {
-                PWMMode::Default => match scheme {
-                    Scheme::Analog => {
-                        write!(buffer, "{}", duty).map_err(|_| io::Error::InvalidInput)?
-                    }
-                    Scheme::Percent => {
-                        let max: usize = self.resource.get_max_duty().into();
-                        write!(buffer, "{}", (duty as f32 / max as f32))
-                            .map_err(|_| io::Error::InvalidInput)?;
-                    }
-                    _ => return Poll::Ready(Err(io::Error::InvalidInput)),
-                },
-                PWMMode::MaxDuty => {
-                    let max: usize = self.resource.get_max_duty().into();
-                    write!(buffer, "{}", max).map_err(|_| io::Error::InvalidInput)?;
+            let max: usize = self.resource.get_max_duty().into();
+            let value = match (mode, scheme) {
+                (PWMMode::Default, Scheme::Analog) => write_value(buf, &to_target_endianess!(duty)),
+                (PWMMode::Default, Scheme::Percent) => {
+                    write_value(buf, &to_target_endianess!(duty as f32 / max as f32))
                 }
-            }
-            Poll::Ready(Ok(buffer.written()))
+                (PWMMode::MaxDuty, Scheme::Analog) => write_value(buf, &to_target_endianess!(max)),
+                _ => Err(io::Error::InvalidInput),
+            };
+            Poll::Ready(value)
         } else {
             Poll::Ready(Err(io::Error::InvalidInput))
         }
@@ -164,7 +155,9 @@ where
     fn path(&self) -> RawPath {
         RawPath::PWM(self.id, PWMMode::Default)
     }
-    fn handle_event(&mut self) {}
+    fn handle_event(&mut self, _event: &TimedEvent) -> bool {
+        true
+    }
 }
 impl<HalPWMPin, Duty> PWMPin<HalPWMPin>
 where
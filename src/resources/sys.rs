@@ -1,8 +1,10 @@
-use crate::{alloc::string::ToString, resources::Resource};
+use crate::events::TimedEvent;
+use crate::{device, events, executor, resources::Resource, supervisor, utilities::ByteWriter};
 use crate::{
     io::{self, SeekFrom},
     schemes::Scheme,
 };
+use core::fmt::Write;
 use core::task::{Context, Poll};
 
 use super::{path::RawPath, ResourceError, ResourceMode};
@@ -11,19 +13,53 @@ use super::{path::RawPath, ResourceError, ResourceMode};
 pub enum SysPaths {
     Heap,
     SysClock,
+    /// Statistics of every task
+    Tasks,
+    /// Fill levels of the task and event queue
+    Queues,
+    /// Reason of the last reset
+    Reset,
+    /// Jobs and deadline misses of the tasks with a deadline
+    Deadlines,
+    /// Events dropped by the full event queue
+    LostEvents,
 }
 impl SysPaths {
     pub fn from_str(path: &str) -> Result<Self, ResourceError> {
         match path {
             "heap" => Ok(SysPaths::Heap),
             "clock" | "sysclock" => Ok(SysPaths::SysClock),
+            "tasks" => Ok(SysPaths::Tasks),
+            "queues" => Ok(SysPaths::Queues),
+            "reset" => Ok(SysPaths::Reset),
+            "deadlines" => Ok(SysPaths::Deadlines),
+            "lost_events" => Ok(SysPaths::LostEvents),
             _ => Err(ResourceError::ParseError),
         }
     }
 }
 pub enum SysResource {
-    Heap { size: usize },
-    SysClock { clock: usize },
+    Heap {
+        size: usize,
+    },
+    SysClock {
+        clock: usize,
+    },
+    /// One line per task of the thread-mode executor (at most 32): id,
+    /// priority, polls, poll time in µs, slow polls and the last wake reason.
+    /// The tasks are updated after every pass of the executor.
+    Tasks,
+    /// One line per queue: current length, capacity (``-`` if unbounded) and
+    /// the maximum length so far
+    Queues,
+    /// The recorded reason of the last reset or ``none``
+    Reset,
+    /// The deadline misses of all tasks so far, followed by one line per
+    /// task with a deadline: id, period and deadline in ms, jobs and misses
+    Deadlines,
+    /// The number of events the event queue dropped,
+    /// see [OverflowPolicy](crate::events::OverflowPolicy)
+    LostEvents,
 }
 
 // TODO: handle schemes and modes
@@ -35,18 +71,22 @@ impl Resource for SysResource {
         _mode: ResourceMode,
         buf: &mut [u8],
     ) -> Poll<Result<usize, io::Error>> {
-        let parsed = match self {
-            SysResource::Heap { size } => size.to_string(),
-            SysResource::SysClock { clock } => clock.to_string(),
+        let mut writer = ByteWriter::new(buf);
+        let written = match self {
+            SysResource::Heap { size } => write!(writer, "{}", size),
+            SysResource::SysClock { clock } => write!(writer, "{}", clock),
+            SysResource::Tasks => write_tasks(&mut writer),
+            SysResource::Queues => write_queues(&mut writer),
+            SysResource::Reset => match supervisor::last_reset_reason() {
+                Some(reason) => write!(writer, "{}", reason),
+                None => write!(writer, "none"),
+            },
+            SysResource::Deadlines => write_deadlines(&mut writer),
+            SysResource::LostEvents => write!(writer, "{}", events::lost()),
         };
-        let parsed = parsed.as_bytes();
-        if buf.len() < parsed.len() {
-            Poll::Ready(Err(io::Error::InvalidInput))
-        } else {
-            for i in 0..parsed.len() {
-                buf[i] = parsed[i]
-            }
-            Poll::Ready(Ok(parsed.len()))
+        match written {
+            Ok(()) => Poll::Ready(Ok(writer.written())),
+            Err(_) => Poll::Ready(Err(io::Error::InvalidInput)),
         }
     }
     fn poll_write(
@@ -87,9 +127,16 @@ impl Resource for SysResource {
         RawPath::Sys(match self {
             SysResource::Heap { .. } => SysPaths::Heap,
             SysResource::SysClock { .. } => SysPaths::SysClock,
+            SysResource::Tasks => SysPaths::Tasks,
+            SysResource::Queues => SysPaths::Queues,
+            SysResource::Reset => SysPaths::Reset,
+            SysResource::Deadlines => SysPaths::Deadlines,
+            SysResource::LostEvents => SysPaths::LostEvents,
         })
     }
-    fn handle_event(&mut self) {}
+    fn handle_event(&mut self, _event: &TimedEvent) -> bool {
+        true
+    }
 }
 impl SysResource {
     pub fn new_heap(size: usize) -> Self {
@@ -100,4 +147,78 @@ impl SysResource {
             clock: clock_in_hertz,
         }
     }
+    pub fn new_tasks() -> Self {
+        Self::Tasks
+    }
+    pub fn new_queues() -> Self {
+        Self::Queues
+    }
+    pub fn new_reset() -> Self {
+        Self::Reset
+    }
+    pub fn new_deadlines() -> Self {
+        Self::Deadlines
+    }
+    pub fn new_lost_events() -> Self {
+        Self::LostEvents
+    }
+}
+
+/// The whole table has to fit into the read buffer.
+/// The executor is busy polling the reader, so the tables show its
+/// [snapshot](executor::task_snapshot).
+fn write_tasks(writer: &mut ByteWriter) -> core::fmt::Result {
+    writeln!(writer, "id priority polls poll_us slow wake")?;
+    for task in (0..).map_while(executor::task_snapshot) {
+        let stats = &task.stats;
+        writeln!(
+            writer,
+            "{} {} {} {} {} {}",
+            task.id,
+            task.priority,
+            stats.polls,
+            device::cycles_to_micros(stats.poll_cycles),
+            stats.slow_polls,
+            stats.last_wake
+        )?;
+    }
+    Ok(())
+}
+
+fn write_deadlines(writer: &mut ByteWriter) -> core::fmt::Result {
+    writeln!(writer, "misses {}", executor::counters().deadline_misses)?;
+    writeln!(writer, "id period_ms deadline_ms jobs misses")?;
+    for task in (0..).map_while(executor::task_snapshot) {
+        if let Some(timing) = task.timing {
+            writeln!(
+                writer,
+                "{} {} {} {} {}",
+                task.id,
+                timing.period.as_millis(),
+                timing.deadline.as_millis(),
+                timing.jobs,
+                timing.misses
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn write_queues(writer: &mut ByteWriter) -> core::fmt::Result {
+    let counters = executor::counters();
+    writeln!(writer, "queue len capacity max")?;
+    write!(writer, "tasks {} ", counters.queue_len)?;
+    // the task queue is only bounded with the task pool
+    match counters.queue_capacity {
+        Some(capacity) => write!(writer, "{}", capacity)?,
+        None => write!(writer, "-")?,
+    }
+    writeln!(writer, " {}", counters.queue_high_water)?;
+    writeln!(
+        writer,
+        "events {} {} {}",
+        events::len(),
+        events::capacity(),
+        events::high_water()
+    )
 }
@@ -0,0 +1,113 @@
+use super::{button::GestureState, path::RawPath, Resource, ResourceMode};
+use crate::device::interrupt;
+use crate::events::{self, Event, EventCursor, EventLog, TimedEvent};
+use crate::io::{self, SeekFrom};
+use crate::{schemes::Scheme, Runtime};
+use core::task::{Context, Poll};
+
+/// A software event defined by the application, e.g. ``event:user/door_open``.
+/// Reading waits for the event to be raised, every reading [ResourceID](super::ResourceID)
+/// sees every raise. Writing any bytes raises it.
+/// Interrupt handlers raise it with [ResourceID::raise](super::ResourceID::raise).
+pub struct UserEvent {
+    index: u8,
+    name: &'static str,
+    /// The recent raises for the subscribed tasks
+    events: EventLog,
+}
+
+impl UserEvent {
+    /// ``index`` is the position of the event in the user event array of the runtime
+    pub fn new(index: u8, name: &'static str) -> Self {
+        UserEvent {
+            index,
+            name,
+            events: EventLog::new(),
+        }
+    }
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// Pushes the user event with the given index, also from interrupt handlers
+#[inline]
+pub(crate) fn raise(index: u8) {
+    interrupt::free(|cs| events::push(Event::User(index), cs))
+}
+
+impl Resource for UserEvent {
+    /// Only ``event:`` reads are supported, see [poll_event](Resource::poll_event)
+    fn poll_read(
+        &mut self,
+        _context: &mut Context,
+        _scheme: Scheme,
+        _mode: ResourceMode,
+        _buf: &mut [u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Poll::Ready(Err(io::Error::InvalidInput))
+    }
+    /// Same format as gpio events: the event byte,
+    /// followed by the timestamp in microseconds if the buffer fits it
+    fn poll_event(
+        &mut self,
+        context: &mut Context<'_>,
+        _mode: ResourceMode,
+        cursor: &mut EventCursor,
+        _gestures: &mut GestureState,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        match self.events.next(cursor) {
+            Some((timestamp, _high)) => Poll::Ready(Ok(events::write_event(0, timestamp, buf))),
+            None => {
+                Runtime::register_waker(&Event::User(self.index), context.waker());
+                Poll::Pending
+            }
+        }
+    }
+    fn poll_write(
+        &mut self,
+        _cx: &mut Context,
+        scheme: Scheme,
+        _mode: ResourceMode,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        if scheme != Scheme::Event {
+            return Poll::Ready(Err(io::Error::InvalidInput));
+        }
+        raise(self.index);
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(
+        &mut self,
+        _: &mut Context<'_>,
+        _scheme: Scheme,
+        _mode: ResourceMode,
+    ) -> Poll<Result<(), io::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_close(
+        &mut self,
+        _: &mut Context<'_>,
+        _scheme: Scheme,
+        _mode: ResourceMode,
+    ) -> Poll<Result<(), io::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_seek(
+        &mut self,
+        _cx: &mut Context,
+        _scheme: Scheme,
+        _mode: ResourceMode,
+        _pos: SeekFrom,
+    ) -> Poll<Result<u64, io::Error>> {
+        Poll::Ready(Err(io::Error::AddrNotAvailable))
+    }
+    fn handle_event(&mut self, event: &TimedEvent) -> bool {
+        self.events.record(event.timestamp, true);
+        true
+    }
+    fn path(&self) -> RawPath {
+        RawPath::User(self.index)
+    }
+}
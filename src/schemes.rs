@@ -1,3 +1,4 @@
+use crate::events;
 use core::str::FromStr;
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Ord, PartialOrd, Hash)]
@@ -14,6 +15,41 @@ pub enum SchemeError {
     ParseError,
 }
 
+/// How the values of a scheme are encoded, selected with ``?fmt=bin|text``.
+/// Reads and writes of a resource id use the same format, without ``fmt``
+/// sys, analog and percent values are text and the values of the other
+/// schemes binary.
+///
+/// | scheme      | bin                                       | text            |
+/// |-------------|-------------------------------------------|-----------------|
+/// | digital     | one byte, 0 is low                        | ``0`` or ``1``  |
+/// | analog      | ``usize``                                 | decimal         |
+/// | percent     | ``f32`` from 0.0 to 1.0                   | decimal         |
+/// | event       | event byte, ``u64`` timestamp in µs       | ``<byte> <µs>`` |
+/// | sys         | -                                         | text            |
+/// | memory, bus | raw bytes                                 | -               |
+///
+/// Binary numbers have the endianess of the target. Event reads leave out
+/// the timestamp if the buffer does not fit it.
+///
+/// Writes to events raise them, the bytes are not decoded.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+pub enum Format {
+    Binary,
+    Text,
+}
+
+impl FromStr for Format {
+    type Err = SchemeError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bin" => Ok(Format::Binary),
+            "text" => Ok(Format::Text),
+            _ => Err(SchemeError::ParseError),
+        }
+    }
+}
+
 #[allow(unused)]
 impl Scheme {
     #[inline]
@@ -74,6 +110,35 @@ impl Scheme {
     }
 }
 
+impl Scheme {
+    /// Sys resources are text. Analog and percent values are text as well,
+    /// they have been read as text before there were formats.
+    /// All other schemes start binary.
+    pub fn default_format(&self) -> Format {
+        match self {
+            Scheme::Sys | Scheme::Analog | Scheme::Percent => Format::Text,
+            _ => Format::Binary,
+        }
+    }
+    pub fn supports(&self, format: Format) -> bool {
+        match (self, format) {
+            (Scheme::Sys, Format::Binary) => false,
+            (Scheme::Memory, Format::Text) | (Scheme::Bus, Format::Text) => false,
+            _ => true,
+        }
+    }
+    /// Size of a binary value, ``None`` if the bytes pass unchanged
+    pub(crate) fn value_size(&self) -> Option<usize> {
+        match self {
+            Scheme::Digital => Some(1),
+            Scheme::Analog => Some(core::mem::size_of::<usize>()),
+            Scheme::Percent => Some(core::mem::size_of::<f32>()),
+            Scheme::Event => Some(events::EVENT_SIZE),
+            Scheme::Sys | Scheme::Memory | Scheme::Bus => None,
+        }
+    }
+}
+
 impl FromStr for Scheme {
     type Err = SchemeError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
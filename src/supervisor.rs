@@ -0,0 +1,208 @@
+//! Restarts tasks that end unexpectedly.
+//!
+//! A [Supervisor] creates a task with a factory closure, awaits it and
+//! spawns a new one from the factory when it ends, as selected by the
+//! [RestartPolicy]. It is a future itself and runs as a task:
+//! ```ignore
+//! Supervisor::new(|| Task::new(read_sensor()), RestartPolicy::Backoff {
+//!     initial: Duration::from_millis(100),
+//!     max: Duration::from_secs(10),
+//! })
+//! .spawn();
+//! ```
+//! With the ``task-pool`` feature the factory takes the tasks from their
+//! pools, e.g. ``|| read_sensor().expect("pool exhausted")``, and the
+//! supervisor is awaited from a pooled task with [Supervisor::run].
+//!
+//! Panics are not isolated on the device, they halt the system.
+//! With [set_reset_on_panic] the device resets instead and reports
+//! [ResetReason::Panic] through [last_reset_reason] and ``sys:sys/reset``
+//! after the restart. On the host (``host`` feature) a panicking task fails
+//! with ``JoinError::Panicked`` and the supervisor restarts it, while a reset
+//! fails the test.
+use crate::task::{Priority, Task, TaskID};
+use crate::{device, time, JoinError, Spawner};
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Spawn a new task as soon as the old one ended
+    Restart,
+    /// Wait ``initial`` before the first restart and double the delay with
+    /// every further restart up to ``max``. A task that ran for at least
+    /// ``max`` starts over with ``initial``.
+    Backoff { initial: Duration, max: Duration },
+    /// Reset the device with [ResetReason::Supervisor]
+    Reset,
+}
+
+/// Why the device was reset the last time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResetReason {
+    /// A task panicked while reset on panic was enabled
+    Panic,
+    /// A supervisor with [RestartPolicy::Reset] escalated the end of a task
+    Supervisor(TaskID),
+    /// [reset] was called by the application
+    Requested,
+    /// The [watchdog](crate::watchdog) was not fed in time
+    Watchdog,
+}
+
+/// Codes of the reset record the device keeps across resets
+const PANIC: u16 = 1;
+const SUPERVISOR: u16 = 2;
+const REQUESTED: u16 = 3;
+
+static RESET_ON_PANIC: AtomicBool = AtomicBool::new(false);
+static mut LAST_RESET: Option<ResetReason> = None;
+
+/// Takes the reason recorded before the last reset.
+/// Called once during the runtime initialization.
+pub(crate) fn init() {
+    // the reset flags of the device are cleared on every boot, so they do not
+    // outlive the reset they belong to. A recorded reason takes precedence.
+    let watchdog = device::take_watchdog_reset();
+    let reason = match device::take_reset_record() {
+        Some((PANIC, _)) => Some(ResetReason::Panic),
+        Some((SUPERVISOR, id)) => Some(ResetReason::Supervisor(TaskID::from_raw(id as usize))),
+        Some((REQUESTED, _)) => Some(ResetReason::Requested),
+        _ if watchdog => Some(ResetReason::Watchdog),
+        _ => None,
+    };
+    unsafe { LAST_RESET = reason }
+}
+
+/// ``None`` after a power on or a reset without a recorded reason
+pub fn last_reset_reason() -> Option<ResetReason> {
+    unsafe { LAST_RESET }
+}
+
+/// Resets the device instead of halting it on a panic.
+/// Disabled by default, so a debugger can inspect the halted system.
+pub fn set_reset_on_panic(reset: bool) {
+    RESET_ON_PANIC.store(reset, Ordering::Relaxed)
+}
+
+/// Records ``reason`` for [last_reset_reason] and resets the device
+pub fn reset(reason: ResetReason) -> ! {
+    match reason {
+        ResetReason::Panic => device::write_reset_record(PANIC, 0),
+        ResetReason::Supervisor(id) => device::write_reset_record(SUPERVISOR, id.into_raw() as u32),
+        ResetReason::Requested => device::write_reset_record(REQUESTED, 0),
+        ResetReason::Watchdog => {}
+    }
+    device::system_reset()
+}
+
+/// Called by the panic handler of the device, it returns if the system
+/// should halt
+#[cfg(not(feature = "host"))]
+pub(crate) fn panicked() {
+    if RESET_ON_PANIC.load(Ordering::Relaxed) {
+        reset(ResetReason::Panic)
+    }
+}
+
+impl fmt::Display for ResetReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResetReason::Panic => write!(f, "panic"),
+            ResetReason::Supervisor(id) => write!(f, "supervisor {}", id),
+            ResetReason::Requested => write!(f, "requested"),
+            ResetReason::Watchdog => write!(f, "watchdog"),
+        }
+    }
+}
+
+/// Keeps a task running, see the [module documentation](self)
+pub struct Supervisor<F> {
+    factory: F,
+    policy: RestartPolicy,
+    priority: Option<Priority>,
+}
+
+impl<F, T> Supervisor<F>
+where
+    F: FnMut() -> Task<T>,
+    T: 'static,
+{
+    pub fn new(factory: F, policy: RestartPolicy) -> Self {
+        Supervisor {
+            factory,
+            policy,
+            priority: None,
+        }
+    }
+    /// Priority of the supervised tasks.
+    /// Without it the tasks keep the priority the factory gave them.
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+    /// Spawns the supervised task and restarts it whenever it ends.
+    /// Only returns if a task cannot be spawned because the spawn queue
+    /// is full.
+    pub async fn run(mut self) {
+        let mut delay = match self.policy {
+            RestartPolicy::Backoff { initial, .. } => initial,
+            _ => Duration::from_secs(0),
+        };
+        loop {
+            let mut task = (self.factory)();
+            if let Some(priority) = self.priority {
+                task = task.with_priority(priority);
+            }
+            let handle = match Spawner::new().spawn(task) {
+                Ok(handle) => handle,
+                Err(error) => {
+                    log::error!("supervisor cannot spawn: {:?}", error);
+                    return;
+                }
+            };
+            let id = handle.id();
+            let started = time::Instant::now();
+            let ended = match handle.await {
+                Ok(_) => "finished",
+                Err(JoinError::Panicked) => "panicked",
+                Err(_) => "was cancelled",
+            };
+            match self.policy {
+                RestartPolicy::Restart => log::warn!("task {} {}, restarting", id, ended),
+                RestartPolicy::Backoff { initial, max } => {
+                    if started.elapsed() >= max {
+                        delay = initial;
+                    }
+                    log::warn!(
+                        "task {} {}, restarting in {} ms",
+                        id,
+                        ended,
+                        delay.as_millis()
+                    );
+                    time::sleep(delay).await;
+                    delay = (delay * 2).min(max);
+                }
+                RestartPolicy::Reset => {
+                    log::error!("task {} {}, resetting", id, ended);
+                    reset(ResetReason::Supervisor(id))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "task-pool"))]
+impl<F, T> Supervisor<F>
+where
+    F: FnMut() -> Task<T> + 'static,
+    T: 'static,
+{
+    /// Spawns the supervisor as a task with the highest priority,
+    /// so restarts are not delayed by the supervised tasks.
+    /// Panics if the spawn queue is full, see [Spawner::spawn].
+    pub fn spawn(self) -> crate::JoinHandle<()> {
+        Task::new(self.run()).with_priority(0).spawn()
+    }
+}
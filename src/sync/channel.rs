@@ -0,0 +1,157 @@
+use super::waker::{WakerList, WakerSlot};
+use crate::device::interrupt::{self, Mutex};
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A bounded multi producer, single consumer queue with ``N`` slots.
+/// [Channel::try_send] does not block, it can be used from interrupt
+/// handlers. Only one task should receive, a second receiver replaces the
+/// waker of the first one.
+pub struct Channel<T, const N: usize> {
+    state: Mutex<RefCell<ChannelState<T, N>>>,
+}
+
+struct ChannelState<T, const N: usize> {
+    buffer: [Option<T>; N],
+    /// Index of the oldest value
+    head: usize,
+    len: usize,
+    receiver: WakerSlot,
+    /// Tasks waiting for a free slot
+    senders: WakerList,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TrySendError<T> {
+    /// The channel is full, the value is handed back
+    Full(T),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+}
+
+impl<T, const N: usize> ChannelState<T, N> {
+    fn push(&mut self, value: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(value);
+        }
+        self.buffer[(self.head + self.len) % N] = Some(value);
+        self.len += 1;
+        self.receiver.wake();
+        Ok(())
+    }
+    fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self.buffer[self.head].take();
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        self.senders.wake_all();
+        value
+    }
+}
+
+impl<T, const N: usize> Channel<T, N> {
+    const EMPTY: Option<T> = None;
+
+    pub const fn new() -> Self {
+        Channel {
+            state: Mutex::new(RefCell::new(ChannelState {
+                buffer: [Self::EMPTY; N],
+                head: 0,
+                len: 0,
+                receiver: WakerSlot::new(),
+                senders: WakerList::new(),
+            })),
+        }
+    }
+    pub fn try_send(&self, value: T) -> Result<(), TrySendError<T>> {
+        interrupt::free(|cs| self.state.borrow(cs).borrow_mut().push(value))
+            .map_err(TrySendError::Full)
+    }
+    /// Waits until a slot is free
+    #[inline]
+    pub fn send(&self, value: T) -> SendFuture<'_, T, N> {
+        SendFuture {
+            channel: self,
+            value: Some(value),
+        }
+    }
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        interrupt::free(|cs| self.state.borrow(cs).borrow_mut().pop()).ok_or(TryRecvError::Empty)
+    }
+    /// Waits for the next value
+    #[inline]
+    pub fn recv(&self) -> RecvFuture<'_, T, N> {
+        RecvFuture { channel: self }
+    }
+    pub fn len(&self) -> usize {
+        interrupt::free(|cs| self.state.borrow(cs).borrow().len)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+}
+
+/// Future returned by [Channel::send]
+pub struct SendFuture<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+    value: Option<T>,
+}
+
+// the value is never pinned
+impl<T, const N: usize> Unpin for SendFuture<'_, T, N> {}
+
+impl<T, const N: usize> Future for SendFuture<'_, T, N> {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        let value = this
+            .value
+            .take()
+            .expect("SendFuture polled after completion");
+        interrupt::free(|cs| {
+            let mut state = this.channel.state.borrow(cs).borrow_mut();
+            match state.push(value) {
+                Ok(()) => Poll::Ready(()),
+                Err(value) => {
+                    this.value = Some(value);
+                    state.senders.register(cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+/// Future returned by [Channel::recv]
+pub struct RecvFuture<'a, T, const N: usize> {
+    channel: &'a Channel<T, N>,
+}
+
+impl<T, const N: usize> Future for RecvFuture<'_, T, N> {
+    type Output = T;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        interrupt::free(|cs| {
+            let mut state = self.channel.state.borrow(cs).borrow_mut();
+            match state.pop() {
+                Some(value) => Poll::Ready(value),
+                None => {
+                    state.receiver.register(cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
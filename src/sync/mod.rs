@@ -0,0 +1,17 @@
+//! Asynchronous synchronization primitives for tasks.
+//!
+//! None of the primitives allocate, they can be placed in statics.
+//! Their state is guarded by ``device::interrupt::free``, so the
+//! non-blocking operations (like [Signal::signal] or [Channel::try_send])
+//! can also be used from interrupt handlers.
+mod channel;
+mod mutex;
+pub mod oneshot;
+mod semaphore;
+mod signal;
+mod waker;
+
+pub use channel::{Channel, RecvFuture, SendFuture, TryRecvError, TrySendError};
+pub use mutex::{Lock, Mutex, MutexGuard};
+pub use semaphore::{Acquire, Semaphore, SemaphorePermit};
+pub use signal::{Signal, Wait};
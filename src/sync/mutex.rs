@@ -0,0 +1,107 @@
+use super::waker::WakerList;
+use crate::device::interrupt;
+use core::{
+    cell::{RefCell, UnsafeCell},
+    future::Future,
+    ops::{Deref, DerefMut},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// A mutex for tasks that can be held across await points.
+/// Waiting tasks are suspended instead of spinning.
+pub struct Mutex<T> {
+    state: interrupt::Mutex<RefCell<MutexState>>,
+    value: UnsafeCell<T>,
+}
+
+struct MutexState {
+    locked: bool,
+    waiters: WakerList,
+}
+
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+impl<T> Mutex<T> {
+    pub const fn new(value: T) -> Self {
+        Mutex {
+            state: interrupt::Mutex::new(RefCell::new(MutexState {
+                locked: false,
+                waiters: WakerList::new(),
+            })),
+            value: UnsafeCell::new(value),
+        }
+    }
+    /// Waits until the mutex is unlocked
+    #[inline]
+    pub fn lock(&self) -> Lock<'_, T> {
+        Lock { mutex: self }
+    }
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            if state.locked {
+                None
+            } else {
+                state.locked = true;
+                Some(MutexGuard { mutex: self })
+            }
+        })
+    }
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+/// Future returned by [Mutex::lock]
+pub struct Lock<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<'a, T> Future for Lock<'a, T> {
+    type Output = MutexGuard<'a, T>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mutex = self.mutex;
+        interrupt::free(|cs| {
+            let mut state = mutex.state.borrow(cs).borrow_mut();
+            if state.locked {
+                state.waiters.register(cx.waker());
+                Poll::Pending
+            } else {
+                state.locked = true;
+                Poll::Ready(MutexGuard { mutex })
+            }
+        })
+    }
+}
+
+/// Unlocks the mutex when dropped
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+
+impl<T> Deref for MutexGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        unsafe { &*self.mutex.value.get() }
+    }
+}
+impl<T> DerefMut for MutexGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.mutex.value.get() }
+    }
+}
+impl<T> Drop for MutexGuard<'_, T> {
+    fn drop(&mut self) {
+        interrupt::free(|cs| {
+            let mut state = self.mutex.state.borrow(cs).borrow_mut();
+            state.locked = false;
+            state.waiters.wake_all();
+        })
+    }
+}
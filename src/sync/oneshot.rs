@@ -0,0 +1,136 @@
+//! Sends a single value from one task or interrupt handler to a task.
+//!
+//! ```ignore
+//! let mut oneshot = Oneshot::new();
+//! let (sender, receiver) = oneshot.split();
+//! sender.send(42).ok();
+//! assert_eq!(receiver.await, Ok(42));
+//! ```
+use super::waker::WakerSlot;
+use crate::device::interrupt::{self, Mutex};
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Storage of a oneshot channel.
+/// [Oneshot::split] creates the sending and the receiving half.
+pub struct Oneshot<T> {
+    state: Mutex<RefCell<OneshotState<T>>>,
+}
+
+struct OneshotState<T> {
+    value: Option<T>,
+    sender_dropped: bool,
+    receiver_dropped: bool,
+    waker: WakerSlot,
+}
+
+/// The sender was dropped without sending a value
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Canceled;
+
+impl<T> Oneshot<T> {
+    pub const fn new() -> Self {
+        Oneshot {
+            state: Mutex::new(RefCell::new(OneshotState {
+                value: None,
+                sender_dropped: false,
+                receiver_dropped: false,
+                waker: WakerSlot::new(),
+            })),
+        }
+    }
+    /// Resets the channel, so it can be used again after both halves
+    /// were dropped
+    pub fn split(&mut self) -> (Sender<'_, T>, Receiver<'_, T>) {
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            state.value = None;
+            state.sender_dropped = false;
+            state.receiver_dropped = false;
+        });
+        (Sender { oneshot: self }, Receiver { oneshot: self })
+    }
+}
+
+pub struct Sender<'a, T> {
+    oneshot: &'a Oneshot<T>,
+}
+
+impl<T> Sender<'_, T> {
+    /// Hands the value back if the receiver was already dropped
+    pub fn send(self, value: T) -> Result<(), T> {
+        interrupt::free(|cs| {
+            let mut state = self.oneshot.state.borrow(cs).borrow_mut();
+            if state.receiver_dropped {
+                Err(value)
+            } else {
+                state.value = Some(value);
+                state.waker.wake();
+                Ok(())
+            }
+        })
+    }
+    /// True if the receiver was dropped
+    pub fn is_closed(&self) -> bool {
+        interrupt::free(|cs| self.oneshot.state.borrow(cs).borrow().receiver_dropped)
+    }
+}
+
+impl<T> Drop for Sender<'_, T> {
+    fn drop(&mut self) {
+        interrupt::free(|cs| {
+            let mut state = self.oneshot.state.borrow(cs).borrow_mut();
+            state.sender_dropped = true;
+            state.waker.wake();
+        })
+    }
+}
+
+/// Awaits the value of the sender
+pub struct Receiver<'a, T> {
+    oneshot: &'a Oneshot<T>,
+}
+
+impl<T> Receiver<'_, T> {
+    /// ``Ok(None)`` if the value was not sent yet
+    pub fn try_recv(&mut self) -> Result<Option<T>, Canceled> {
+        interrupt::free(|cs| self.oneshot.state.borrow(cs).borrow_mut().take())
+    }
+}
+
+impl<T> OneshotState<T> {
+    fn take(&mut self) -> Result<Option<T>, Canceled> {
+        match self.value.take() {
+            Some(value) => Ok(Some(value)),
+            None if self.sender_dropped => Err(Canceled),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T> Future for Receiver<'_, T> {
+    type Output = Result<T, Canceled>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        interrupt::free(|cs| {
+            let mut state = self.oneshot.state.borrow(cs).borrow_mut();
+            match state.take() {
+                Ok(Some(value)) => Poll::Ready(Ok(value)),
+                Err(canceled) => Poll::Ready(Err(canceled)),
+                Ok(None) => {
+                    state.waker.register(cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+impl<T> Drop for Receiver<'_, T> {
+    fn drop(&mut self) {
+        interrupt::free(|cs| self.oneshot.state.borrow(cs).borrow_mut().receiver_dropped = true)
+    }
+}
@@ -0,0 +1,97 @@
+use super::waker::WakerList;
+use crate::device::interrupt::{self, Mutex};
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Limits how many tasks can use a resource at the same time.
+/// [Semaphore::add_permits] does not block, it can be used from
+/// interrupt handlers.
+pub struct Semaphore {
+    state: Mutex<RefCell<SemaphoreState>>,
+}
+
+struct SemaphoreState {
+    permits: usize,
+    waiters: WakerList,
+}
+
+impl Semaphore {
+    pub const fn new(permits: usize) -> Self {
+        Semaphore {
+            state: Mutex::new(RefCell::new(SemaphoreState {
+                permits,
+                waiters: WakerList::new(),
+            })),
+        }
+    }
+    /// Waits until a permit is available
+    #[inline]
+    pub fn acquire(&self) -> Acquire<'_> {
+        Acquire { semaphore: self }
+    }
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            if state.permits == 0 {
+                None
+            } else {
+                state.permits -= 1;
+                Some(SemaphorePermit { semaphore: self })
+            }
+        })
+    }
+    pub fn add_permits(&self, permits: usize) {
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            state.permits += permits;
+            state.waiters.wake_all();
+        })
+    }
+    pub fn available_permits(&self) -> usize {
+        interrupt::free(|cs| self.state.borrow(cs).borrow().permits)
+    }
+}
+
+/// Future returned by [Semaphore::acquire]
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = SemaphorePermit<'a>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let semaphore = self.semaphore;
+        interrupt::free(|cs| {
+            let mut state = semaphore.state.borrow(cs).borrow_mut();
+            if state.permits == 0 {
+                state.waiters.register(cx.waker());
+                Poll::Pending
+            } else {
+                state.permits -= 1;
+                Poll::Ready(SemaphorePermit { semaphore })
+            }
+        })
+    }
+}
+
+/// Returns its permit to the semaphore when dropped
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl SemaphorePermit<'_> {
+    /// Keeps the permit, the semaphore has one permit less from now on
+    pub fn forget(self) {
+        core::mem::forget(self)
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.add_permits(1)
+    }
+}
@@ -0,0 +1,76 @@
+use super::waker::WakerSlot;
+use crate::device::interrupt::{self, Mutex};
+use core::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Hands a value to one waiting task.
+/// Signaling again before the value was taken overrides it.
+/// [Signal::signal] does not block, it can be used from interrupt handlers.
+pub struct Signal<T> {
+    state: Mutex<RefCell<SignalState<T>>>,
+}
+
+struct SignalState<T> {
+    value: Option<T>,
+    waker: WakerSlot,
+}
+
+impl<T> Signal<T> {
+    pub const fn new() -> Self {
+        Signal {
+            state: Mutex::new(RefCell::new(SignalState {
+                value: None,
+                waker: WakerSlot::new(),
+            })),
+        }
+    }
+    /// Stores the value and wakes the waiting task
+    pub fn signal(&self, value: T) {
+        interrupt::free(|cs| {
+            let mut state = self.state.borrow(cs).borrow_mut();
+            state.value = Some(value);
+            state.waker.wake();
+        })
+    }
+    /// Drops a value that was not taken yet
+    pub fn reset(&self) {
+        interrupt::free(|cs| self.state.borrow(cs).borrow_mut().value = None)
+    }
+    pub fn signaled(&self) -> bool {
+        interrupt::free(|cs| self.state.borrow(cs).borrow().value.is_some())
+    }
+    pub fn try_take(&self) -> Option<T> {
+        interrupt::free(|cs| self.state.borrow(cs).borrow_mut().value.take())
+    }
+    /// Waits for the next value.
+    /// Only one task can wait at a time, a second one replaces the first.
+    #[inline]
+    pub fn wait(&self) -> Wait<'_, T> {
+        Wait { signal: self }
+    }
+}
+
+/// Future returned by [Signal::wait]
+pub struct Wait<'a, T> {
+    signal: &'a Signal<T>,
+}
+
+impl<T> Future for Wait<'_, T> {
+    type Output = T;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        interrupt::free(|cs| {
+            let mut state = self.signal.state.borrow(cs).borrow_mut();
+            match state.value.take() {
+                Some(value) => Poll::Ready(value),
+                None => {
+                    state.waker.register(cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
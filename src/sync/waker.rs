@@ -0,0 +1,62 @@
+use core::task::Waker;
+use heapless::consts::*;
+use heapless::Vec;
+
+/// The waker of a single waiting task.
+/// Registering a new waker replaces the old one.
+pub(crate) struct WakerSlot {
+    waker: Option<Waker>,
+}
+
+impl WakerSlot {
+    pub(crate) const fn new() -> Self {
+        WakerSlot { waker: None }
+    }
+    pub(crate) fn register(&mut self, waker: &Waker) {
+        match &self.waker {
+            Some(registered) if registered.will_wake(waker) => {}
+            _ => self.waker = Some(waker.clone()),
+        }
+    }
+    pub(crate) fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake()
+        }
+    }
+}
+
+/// Wakers of several waiting tasks.
+/// If the list is full, all registered tasks are woken to make room.
+/// They register again when they are polled and still have to wait.
+pub(crate) struct WakerList {
+    wakers: Vec<Waker, U8>,
+}
+
+impl WakerList {
+    pub(crate) const fn new() -> Self {
+        WakerList {
+            // heapless only provides const construction through the ``i`` module
+            wakers: Vec(heapless::i::Vec::new()),
+        }
+    }
+    pub(crate) fn register(&mut self, waker: &Waker) {
+        if self
+            .wakers
+            .iter()
+            .any(|registered| registered.will_wake(waker))
+        {
+            return;
+        }
+        if let Err(waker) = self.wakers.push(waker.clone()) {
+            self.wake_all();
+            self.wakers.push(waker).ok();
+        }
+    }
+    /// Waiting tasks may have been dropped in the meantime, so every
+    /// waiter is woken and tries again.
+    pub(crate) fn wake_all(&mut self) {
+        while let Some(waker) = self.wakers.pop() {
+            waker.wake()
+        }
+    }
+}
@@ -1,82 +1,733 @@
 use super::*;
+use crate::device::interrupt::{self, Mutex};
+use crate::time::Instant;
+#[cfg(not(feature = "task-pool"))]
+use alloc::{boxed::Box, sync::Arc};
+use core::cell::RefCell;
 use core::sync::atomic::AtomicUsize;
 use core::task::{RawWaker, RawWakerVTable, Waker};
+use core::time::Duration;
+use pin_project::{pin_project, pinned_drop};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct TaskID(usize);
 
-pub struct Task {
+impl TaskID {
+    #[inline]
+    pub(crate) fn from_raw(id: usize) -> Self {
+        TaskID(id)
+    }
+    #[inline]
+    pub(crate) fn into_raw(self) -> usize {
+        self.0
+    }
+}
+
+/// Zero is the highest priority.
+/// Ready tasks with a higher priority are always polled first.
+pub type Priority = u8;
+
+/// Tasks without an explicit priority get the lowest one
+const DEFAULT_PRIORITY: Priority = Priority::MAX;
+
+/// How the executor picks the next ready task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheduling {
+    /// The highest priority first, FIFO among equal priorities
+    Priority,
+    /// Earliest deadline first (EDF) among the tasks with a deadline, see
+    /// [Task::with_deadline]. Tasks without a deadline only run if no task
+    /// with a deadline is ready, by priority among themselves.
+    EarliestDeadline,
+}
+
+impl Default for Scheduling {
+    fn default() -> Self {
+        Scheduling::Priority
+    }
+}
+
+/// Period and relative deadline of a periodic task and the state of its
+/// current job. A job ends with [next_period].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Timing {
+    pub(crate) period: Duration,
+    pub(crate) deadline: Duration,
+    /// Release time of the current job
+    release: Instant,
+    pub(crate) jobs: u32,
+    pub(crate) misses: u32,
+    /// Set once the current job is counted as a miss
+    missed: bool,
+}
+
+impl Timing {
+    #[inline]
+    fn absolute_deadline(&self) -> Instant {
+        self.release + self.deadline
+    }
+    /// Counts a miss as soon as the deadline of the current job passed,
+    /// also while the job waits for something.
+    /// Returns true if the miss was not counted before.
+    pub(crate) fn check_deadline(&mut self, now: Instant) -> bool {
+        if self.missed || now <= self.absolute_deadline() {
+            return false;
+        }
+        self.missed = true;
+        self.misses = self.misses.wrapping_add(1);
+        true
+    }
+    /// The deadline of the current job, ``None`` if it is missed already
+    #[inline]
+    pub(crate) fn pending_deadline(&self) -> Option<Instant> {
+        match self.missed {
+            true => None,
+            false => Some(self.absolute_deadline()),
+        }
+    }
+    /// Ends the current job and releases the next one one period later.
+    /// Returns the release time and if the job missed its deadline.
+    pub(crate) fn complete_job(&mut self, now: Instant) -> (Instant, bool) {
+        self.check_deadline(now);
+        let missed = core::mem::replace(&mut self.missed, false);
+        self.jobs = self.jobs.wrapping_add(1);
+        self.release += self.period;
+        (self.release, missed)
+    }
+}
+
+/// A future that can be spawned on the runtime.
+/// ``T`` is the output of the future, it can be awaited through the
+/// [JoinHandle] returned by [Task::spawn].
+pub struct Task<T: 'static = ()> {
+    raw: RawTask,
+    join: JoinHandle<T>,
+}
+
+/// The type erased future of a task.
+/// With the ``task-pool`` feature it lives in a static [TaskPool](crate::pool::TaskPool)
+/// slot instead of the heap.
+#[cfg(not(feature = "task-pool"))]
+pub(crate) type TaskFuture = Pin<Box<dyn Future<Output = ()>>>;
+#[cfg(feature = "task-pool")]
+pub(crate) type TaskFuture = Pin<pool::PoolBox>;
+
+/// The state shared by a task and its JoinHandle.
+/// The task and the handle can be on executors that preempt each other,
+/// so it is only accessed in a critical section, see [with_shared].
+pub(crate) type JoinCell<T> = Mutex<RefCell<JoinShared<T>>>;
+
+/// Reference to the [JoinCell] of a task
+#[cfg(not(feature = "task-pool"))]
+pub(crate) type Shared<T> = Arc<JoinCell<T>>;
+#[cfg(feature = "task-pool")]
+pub(crate) type Shared<T> = pool::PoolRc<JoinCell<T>>;
+
+#[inline]
+fn with_shared<T, R>(shared: &Shared<T>, f: impl FnOnce(&mut JoinShared<T>) -> R) -> R {
+    interrupt::free(|cs| f(&mut shared.borrow(cs).borrow_mut()))
+}
+
+/// Why a task was woken the last time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum WakeReason {
+    Spawned,
+    Event(Event),
+    Timer,
+    /// Woken by another task or an interrupt handler
+    Waker,
+    /// Woken from an interrupt handler
+    Interrupt,
+}
+
+/// Runtime statistics of a task, exposed through ``sys:sys/tasks``
+#[derive(Debug, Clone)]
+pub(crate) struct TaskStats {
+    pub(crate) polls: u32,
+    /// Core clock cycles spent polling the task
+    pub(crate) poll_cycles: u64,
+    /// Core clock cycles of the last poll
+    pub(crate) last_poll_cycles: u32,
+    /// Polls that exceeded the slow poll threshold of the executor
+    pub(crate) slow_polls: u32,
+    /// Slow polls in a row
+    pub(crate) slow_streak: u32,
+    pub(crate) last_wake: WakeReason,
+}
+
+/// The type erased task as it is stored in the executor
+pub(crate) struct RawTask {
     id: TaskID,
+    /// Slot of the executor that polls the task
+    executor: usize,
+    priority: Priority,
+    /// Only set for tasks with a deadline
+    timing: Option<Timing>,
+    stats: TaskStats,
+    /// Set while the task is in the task queue, so it is queued at most once
+    queued: bool,
     /// TODO: Maybe consider stack pinning:
     /// https://doc.rust-lang.org/stable/std/pin/index.html#projections-and-structural-pinning
     /// as mentioned in phil oppps blog:
     /// https://os.phil-opp.com/async-await/#pinning
-    future: Pin<Box<dyn Future<Output = ()>>>,
+    future: TaskFuture,
 }
 
-fn raw_waker(task: *const ()) -> RawWaker {
-    fn clone(task: *const ()) -> RawWaker {
-        raw_waker(task)
-    }
-    fn wake(task: *const ()) {
-        wake_by_ref(task)
-    }
-    fn wake_by_ref(task: *const ()) {
-        unsafe { Runtime::get().executor.reque((*(task as *const Task)).id) }
+/// Wakers only store the id of their task and the slot of its executor,
+/// so they stay valid if the task moves or is removed from the executor and
+/// creating them never allocates.
+static TASK_WAKER_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(clone_waker, wake_task, wake_task, drop_waker);
+
+/// The executor slot is stored in the lowest bits of the waker data
+const EXECUTOR_BITS: usize = 2;
+const EXECUTOR_MASK: usize = (1 << EXECUTOR_BITS) - 1;
+/// Largest id that fits into the waker data
+const MAX_TASK_ID: usize = usize::MAX >> EXECUTOR_BITS;
+
+fn task_waker(id: TaskID, executor: usize) -> Waker {
+    let data = id.0 << EXECUTOR_BITS | executor;
+    unsafe { Waker::from_raw(RawWaker::new(data as *const (), &TASK_WAKER_VTABLE)) }
+}
+unsafe fn clone_waker(data: *const ()) -> RawWaker {
+    RawWaker::new(data, &TASK_WAKER_VTABLE)
+}
+unsafe fn wake_task(data: *const ()) {
+    let data = data as usize;
+    executor::wake(TaskID(data >> EXECUTOR_BITS), data & EXECUTOR_MASK)
+}
+unsafe fn drop_waker(_: *const ()) {}
+
+impl core::fmt::Display for TaskID {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.0)
     }
-    fn drop(_task: *const ()) {}
+}
 
-    let vtable = &RawWakerVTable::new(clone, wake, wake_by_ref, drop);
-    RawWaker::new(task, vtable)
+impl core::fmt::Display for WakeReason {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WakeReason::Spawned => write!(f, "spawned"),
+            WakeReason::Event(event) => write!(f, "{:?}", event),
+            WakeReason::Timer => write!(f, "timer"),
+            WakeReason::Waker => write!(f, "waker"),
+            WakeReason::Interrupt => write!(f, "interrupt"),
+        }
+    }
 }
 
 impl TaskID {
     #[inline]
     fn new() -> Self {
         static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
-        TaskID(NEXT_ID.fetch_add(1, core::sync::atomic::Ordering::Relaxed))
+        // the id shares the waker data with the executor slot, so it wraps
+        // around before it would lose its upper bits
+        TaskID(NEXT_ID.fetch_add(1, core::sync::atomic::Ordering::Relaxed) & MAX_TASK_ID)
     }
 }
 
-impl Task {
-    /// zero is highest priority
-    pub fn new(future: impl Future<Output = ()> + 'static) -> Task {
+impl<T: 'static> Task<T> {
+    /// Allocates the future on the heap.
+    /// With the ``task-pool`` feature tasks are created by a
+    /// [TaskPool](crate::pool::TaskPool) instead.
+    #[cfg(not(feature = "task-pool"))]
+    pub fn new(future: impl Future<Output = T> + 'static) -> Task<T> {
+        let shared = Arc::new(Mutex::new(RefCell::new(JoinShared::new())));
+        let future = Box::pin(Joinable::new(future, shared.clone()));
+        Task::from_parts(future, shared)
+    }
+    /// ``future`` has to be a [Joinable] that reports to ``shared``
+    pub(crate) fn from_parts(future: TaskFuture, shared: Shared<T>) -> Task<T> {
+        let id = TaskID::new();
         Task {
-            id: TaskID::new(),
-            future: Box::pin(future),
+            raw: RawTask {
+                id,
+                executor: executor::THREAD,
+                priority: DEFAULT_PRIORITY,
+                timing: None,
+                stats: TaskStats {
+                    polls: 0,
+                    poll_cycles: 0,
+                    last_poll_cycles: 0,
+                    slow_polls: 0,
+                    slow_streak: 0,
+                    last_wake: WakeReason::Spawned,
+                },
+                // spawning enqueues the task
+                queued: true,
+                future,
+            },
+            join: JoinHandle {
+                id,
+                executor: executor::THREAD,
+                shared,
+            },
         }
     }
+    /// zero is highest priority
+    #[inline]
+    pub fn with_priority(mut self, priority: Priority) -> Task<T> {
+        self.raw.priority = priority;
+        self
+    }
+    #[inline]
+    pub fn priority(&self) -> Priority {
+        self.raw.priority
+    }
+    /// Makes the task periodic: a job is released every ``period`` and has
+    /// to end with [next_period] within ``deadline`` after its release.
+    /// The first job is released when the task is spawned.
+    /// With [Scheduling::EarliestDeadline] the executor polls the ready task
+    /// with the earliest deadline first, late jobs are counted as deadline
+    /// misses in ``sys:sys/deadlines`` in every mode. A job is counted as soon
+    /// as its deadline passed, also if it waits for something.
+    #[inline]
+    pub fn with_deadline(mut self, period: Duration, deadline: Duration) -> Task<T> {
+        self.raw.timing = Some(Timing {
+            period,
+            deadline,
+            release: Instant::now(),
+            jobs: 0,
+            misses: 0,
+            missed: false,
+        });
+        self
+    }
+    /// The returned handle can be awaited for the output of the task.
+    /// Dropping the handle detaches the task, it keeps running.
+    /// Panics if the spawn queue is full, see [Spawner::spawn].
+    #[inline]
+    pub fn spawn(self) -> JoinHandle<T> {
+        Spawner::new().spawn(self).expect("spawn queue full")
+    }
+    #[inline]
+    pub(crate) fn into_raw(self) -> (RawTask, JoinHandle<T>) {
+        (self.raw, self.join)
+    }
+}
+
+/// A task whose future and output are ``Send``.
+/// Only these tasks can be spawned on an [InterruptExecutor](crate::InterruptExecutor),
+/// their futures are polled in interrupt context. They can be spawned on
+/// the thread-mode executor as well with [into_task](SendTask::into_task).
+pub struct SendTask<T: 'static = ()>(Task<T>);
+
+// the future of the task is Send, it was checked before it was type erased
+unsafe impl<T: Send> Send for SendTask<T> {}
+
+impl<T: Send + 'static> SendTask<T> {
+    /// Allocates the future on the heap, see [Task::new]
+    #[cfg(not(feature = "task-pool"))]
+    pub fn new(future: impl Future<Output = T> + Send + 'static) -> SendTask<T> {
+        SendTask(Task::new(future))
+    }
+    /// The caller ensures that the future of ``task`` and its output are Send
+    #[cfg(feature = "task-pool")]
+    #[inline]
+    pub(crate) unsafe fn from_task(task: Task<T>) -> SendTask<T> {
+        SendTask(task)
+    }
+    /// see [Task::with_priority]
+    #[inline]
+    pub fn with_priority(self, priority: Priority) -> SendTask<T> {
+        SendTask(self.0.with_priority(priority))
+    }
+    /// see [Task::with_deadline]
+    #[inline]
+    pub fn with_deadline(self, period: Duration, deadline: Duration) -> SendTask<T> {
+        SendTask(self.0.with_deadline(period, deadline))
+    }
+    #[inline]
+    pub fn into_task(self) -> Task<T> {
+        self.0
+    }
+}
+
+/// Spawns tasks from running tasks and interrupt handlers.
+/// The tasks are handed to the executor through a lock-free queue.
+/// The executor is chosen with the spawner: [Runtime::spawner] spawns on the
+/// thread-mode executor, [InterruptExecutor::spawner](crate::InterruptExecutor::spawner)
+/// on an interrupt executor.
+///
+/// Interrupt handlers should spawn tasks from a [TaskPool](crate::pool::TaskPool),
+/// the heap allocator must not be used in interrupts.
+#[derive(Debug, Clone, Copy)]
+pub struct Spawner {
+    /// Slot of the executor
+    executor: usize,
+}
+
+impl Spawner {
+    #[inline]
+    pub(crate) const fn new() -> Self {
+        Self::on(executor::THREAD)
+    }
+    #[inline]
+    pub(crate) const fn on(executor: usize) -> Self {
+        Spawner { executor }
+    }
+    /// Fails with ``RuntimeError::TaskQueueIsFull`` if the spawn queue is full
+    /// and with ``RuntimeError::UninitializedAccess`` if the executor is not
+    /// started. The task is dropped in both cases.
+    pub fn spawn<T: 'static>(&self, task: Task<T>) -> Result<JoinHandle<T>, RuntimeError> {
+        let (mut task, mut handle) = task.into_raw();
+        task.executor = self.executor;
+        handle.executor = self.executor;
+        executor::spawn(task, self.executor).map(|()| handle)
+    }
+}
+
+/// Spawns [SendTask]s on an [InterruptExecutor](crate::InterruptExecutor),
+/// see [Spawner]
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptSpawner(Spawner);
+
+impl InterruptSpawner {
+    #[inline]
+    pub(crate) const fn on(executor: usize) -> Self {
+        InterruptSpawner(Spawner::on(executor))
+    }
+    /// see [Spawner::spawn]
+    #[inline]
+    pub fn spawn<T: Send + 'static>(
+        &self,
+        task: SendTask<T>,
+    ) -> Result<JoinHandle<T>, RuntimeError> {
+        self.0.spawn(task.0)
+    }
+}
+
+impl RawTask {
     pub(crate) fn id(&self) -> TaskID {
         self.id
     }
     #[inline]
-    pub fn spawn(self) {
-        Runtime::get().spawn_task(self)
+    pub(crate) fn priority(&self) -> Priority {
+        self.priority
+    }
+    #[inline]
+    pub(crate) fn timing(&self) -> Option<Timing> {
+        self.timing
+    }
+    #[inline]
+    pub(crate) fn set_timing(&mut self, timing: Option<Timing>) {
+        self.timing = timing
+    }
+    /// The first job is released when the executor takes over the task
+    #[inline]
+    pub(crate) fn release(&mut self, now: Instant) {
+        if let Some(timing) = &mut self.timing {
+            timing.release = now
+        }
+    }
+    /// See [Timing::check_deadline], false for tasks without a deadline
+    #[inline]
+    pub(crate) fn check_deadline(&mut self, now: Instant) -> bool {
+        match &mut self.timing {
+            Some(timing) => timing.check_deadline(now),
+            None => false,
+        }
+    }
+    /// Sort key of the task queue, tasks without a deadline sort last
+    #[inline]
+    pub(crate) fn deadline_ticks(&self) -> u64 {
+        match &self.timing {
+            Some(timing) => timing.absolute_deadline().ticks(),
+            None => u64::MAX,
+        }
+    }
+    #[inline]
+    pub(crate) fn stats(&self) -> &TaskStats {
+        &self.stats
+    }
+    #[inline]
+    pub(crate) fn woken(&mut self, reason: WakeReason) {
+        self.stats.last_wake = reason
     }
+    /// Polls the future and records the time it took
     #[inline]
     pub(crate) fn poll(&mut self, context: &mut Context) -> Poll<()> {
-        self.future.as_mut().poll(context)
+        let start = device::cycles();
+        #[cfg(not(feature = "host"))]
+        let poll = self.future.as_mut().poll(context);
+        // the host isolates panics: the executor removes the task and
+        // dropping its future reports the panic to the JoinHandle.
+        // A simulated reset ends the test instead.
+        #[cfg(feature = "host")]
+        let poll = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.future.as_mut().poll(context)
+        }))
+        .unwrap_or_else(|panic| {
+            if device::host::resetting() {
+                std::panic::resume_unwind(panic)
+            }
+            Poll::Ready(())
+        });
+        let cycles = device::cycles().wrapping_sub(start);
+        self.stats.polls = self.stats.polls.wrapping_add(1);
+        self.stats.poll_cycles += cycles as u64;
+        self.stats.last_poll_cycles = cycles;
+        poll
+    }
+    /// Checks the last poll against ``threshold_us``.
+    /// Returns the number of slow polls in a row.
+    #[inline]
+    pub(crate) fn count_slow_poll(&mut self, threshold_us: u64) -> u32 {
+        let stats = &mut self.stats;
+        if device::cycles_to_micros(stats.last_poll_cycles as u64) >= threshold_us {
+            stats.slow_polls = stats.slow_polls.wrapping_add(1);
+            stats.slow_streak += 1;
+        } else {
+            stats.slow_streak = 0;
+        }
+        stats.slow_streak
     }
-    /// moves task into waker
     #[inline]
     pub(crate) fn waker(&self) -> Waker {
-        unsafe { Waker::from_raw(raw_waker(self as *const Task as *const ())) }
+        task_waker(self.id, self.executor)
+    }
+    /// Marks the task as queued.
+    /// Returns false if it already was in the task queue.
+    #[inline]
+    pub(crate) fn enqueued(&mut self) -> bool {
+        !core::mem::replace(&mut self.queued, true)
+    }
+    /// Has to be called when the task is taken from the task queue,
+    /// before it is polled. Wakes during the poll enqueue the task again.
+    #[inline]
+    pub(crate) fn dequeued(&mut self) {
+        self.queued = false
     }
 }
 
-impl Eq for Task {}
-impl PartialEq for Task {
+impl Eq for RawTask {}
+impl PartialEq for RawTask {
     fn eq(&self, other: &Self) -> bool {
         core::ptr::eq(&self.id, &other.id)
     }
 }
-// impl Ord for Task {
-//     fn cmp(&self, other: &Self) -> core::cmp::Ordering {
-//         self.priority.cmp(&other.priority)
-//     }
-// }
-// impl PartialOrd for Task {
-//     fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
-//         Some(self.cmp(other))
-//     }
-// }
+
+/// Why a task did not produce an output
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum JoinError {
+    /// The task was dropped before it finished
+    Cancelled,
+    /// The task was dropped while it was polled, i.e. during unwinding.
+    /// Without unwinding support (the default on the device)
+    /// a panic halts the system instead.
+    Panicked,
+}
+
+pub(crate) enum JoinState<T> {
+    Running,
+    Finished(T),
+    Failed(JoinError),
+    /// The result was already handed out through the JoinHandle
+    Taken,
+}
+
+/// State shared by a spawned task and its JoinHandle
+pub(crate) struct JoinShared<T> {
+    state: JoinState<T>,
+    /// Waker of the task awaiting the JoinHandle
+    waker: Option<Waker>,
+    /// Set by JoinHandle::abort, the task finishes on its next poll
+    aborted: bool,
+}
+
+impl<T> JoinShared<T> {
+    pub(crate) fn new() -> Self {
+        JoinShared {
+            state: JoinState::Running,
+            waker: None,
+            aborted: false,
+        }
+    }
+    /// Returns the waker of the awaiting task,
+    /// it is woken outside of the critical section
+    #[must_use]
+    fn complete(&mut self, state: JoinState<T>) -> Option<Waker> {
+        self.state = state;
+        self.waker.take()
+    }
+}
+
+/// Wraps the future of a task and reports its result to the JoinHandle
+#[pin_project(PinnedDrop)]
+pub(crate) struct Joinable<F: Future> {
+    #[pin]
+    future: F,
+    shared: Shared<F::Output>,
+    /// Set while the inner future is polled, so a drop in between must
+    /// come from a panic
+    polling: bool,
+}
+
+impl<F: Future> Joinable<F> {
+    pub(crate) fn new(future: F, shared: Shared<F::Output>) -> Self {
+        Joinable {
+            future,
+            shared,
+            polling: false,
+        }
+    }
+}
+
+impl<F: Future> Future for Joinable<F> {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.project();
+        if with_shared(this.shared, |shared| shared.aborted) {
+            // the executor removes finished tasks and drops the inner future
+            return Poll::Ready(());
+        }
+        *this.polling = true;
+        let poll = this.future.poll(cx);
+        *this.polling = false;
+        match poll {
+            Poll::Ready(output) => {
+                let waker = with_shared(this.shared, |shared| {
+                    shared.complete(JoinState::Finished(output))
+                });
+                if let Some(waker) = waker {
+                    waker.wake()
+                }
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[pinned_drop]
+impl<F: Future> PinnedDrop for Joinable<F> {
+    fn drop(self: Pin<&mut Self>) {
+        let this = self.project();
+        let error = if *this.polling {
+            JoinError::Panicked
+        } else {
+            JoinError::Cancelled
+        };
+        let waker = with_shared(this.shared, |shared| match shared.state {
+            JoinState::Running => shared.complete(JoinState::Failed(error)),
+            _ => None,
+        });
+        if let Some(waker) = waker {
+            waker.wake()
+        }
+    }
+}
+
+/// Lets the executor poll other ready tasks before the current task continues.
+/// The task is queued again behind all ready tasks of the same priority.
+/// Tasks with a higher priority still run first, a lower priority task only
+/// runs if no higher priority task is ready.
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+pub struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            Poll::Ready(())
+        } else {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+/// Ends the current job of a task with a deadline and waits for the release
+/// of the next one, see [Task::with_deadline]. A job that overran its period
+/// is followed by the next one right away.
+/// Completes immediately in tasks without a deadline.
+pub fn next_period() -> NextPeriod {
+    NextPeriod { release: None }
+}
+
+pub struct NextPeriod {
+    release: Option<time::Sleep>,
+}
+
+impl Future for NextPeriod {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.release.is_none() {
+            match executor::complete_job() {
+                Some(release) => self.release = Some(time::sleep_until(release)),
+                None => return Poll::Ready(()),
+            }
+        }
+        match &mut self.release {
+            Some(release) => Pin::new(release).poll(cx),
+            None => Poll::Ready(()),
+        }
+    }
+}
+
+/// Awaits the output of a spawned task
+pub struct JoinHandle<T> {
+    id: TaskID,
+    /// Slot of the executor of the task
+    executor: usize,
+    shared: Shared<T>,
+}
+
+impl<T> JoinHandle<T> {
+    #[inline]
+    pub fn id(&self) -> TaskID {
+        self.id
+    }
+    /// Cancels the task.
+    /// The executor drops its future and unregisters its wakers the next
+    /// time it gets to the task. Awaiting the handle then returns
+    /// ``Err(JoinError::Cancelled)``. Finished tasks are not affected.
+    pub fn abort(&self) {
+        let running = with_shared(&self.shared, |shared| match shared.state {
+            JoinState::Running => {
+                shared.aborted = true;
+                true
+            }
+            _ => false,
+        });
+        if running {
+            executor::wake(self.id, self.executor);
+        }
+    }
+    /// True if the task finished, failed or its result was already taken
+    pub fn is_finished(&self) -> bool {
+        with_shared(&self.shared, |shared| match shared.state {
+            JoinState::Running => false,
+            _ => true,
+        })
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let state = with_shared(&self.shared, |shared| {
+            match core::mem::replace(&mut shared.state, JoinState::Taken) {
+                JoinState::Running => {
+                    shared.state = JoinState::Running;
+                    shared.waker = Some(cx.waker().clone());
+                    JoinState::Running
+                }
+                state => state,
+            }
+        });
+        match state {
+            JoinState::Running => Poll::Pending,
+            JoinState::Finished(output) => Poll::Ready(Ok(output)),
+            JoinState::Failed(error) => Poll::Ready(Err(error)),
+            JoinState::Taken => panic!("JoinHandle polled after completion"),
+        }
+    }
+}
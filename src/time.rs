@@ -0,0 +1,246 @@
+//! Asynchronous timers backed by the monotonic device clock.
+//!
+//! The clock ticks with ``TICKS_PER_SECOND`` (once per millisecond).
+//! Sleeping tasks register their deadline at the executor. The device
+//! clock pushes an ``Event::Timer`` as soon as the earliest deadline is
+//! reached, so waiting tasks do not need to busy-poll.
+use crate::device;
+use crate::Runtime;
+use core::{
+    future::Future,
+    ops::{Add, AddAssign},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use pin_project::pin_project;
+
+pub use crate::device::TICKS_PER_SECOND;
+
+/// A point in time measured by the monotonic device clock.
+/// It counts clock ticks since the runtime was initialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    #[inline]
+    pub fn now() -> Self {
+        Instant(device::now())
+    }
+    #[inline]
+    pub fn ticks(&self) -> u64 {
+        self.0
+    }
+    /// Saturates to zero if ``earlier`` is later than self
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        ticks_to_duration(self.0.saturating_sub(earlier.0))
+    }
+    #[inline]
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+    fn add(self, duration: Duration) -> Instant {
+        Instant(self.0.saturating_add(duration_to_ticks(duration)))
+    }
+}
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration
+    }
+}
+
+/// Parses a duration like ``500us``, ``20ms`` or ``1s``
+pub(crate) fn parse_duration(duration: &str) -> Option<Duration> {
+    let unit_start = duration.find(|c: char| !c.is_ascii_digit())?;
+    let value = duration[..unit_start].parse::<u64>().ok()?;
+    match &duration[unit_start..] {
+        "us" => Some(Duration::from_micros(value)),
+        "ms" => Some(Duration::from_millis(value)),
+        "s" => Some(Duration::from_secs(value)),
+        _ => None,
+    }
+}
+
+/// A point in time with microsecond resolution, e.g. when an event occurred.
+/// It counts from the same start as [Instant], the part below a clock tick
+/// is measured with the core cycle counter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(u64);
+
+impl Timestamp {
+    #[inline]
+    pub fn now() -> Self {
+        Timestamp(device::timestamp_micros())
+    }
+    #[inline]
+    pub const fn from_micros(micros: u64) -> Self {
+        Timestamp(micros)
+    }
+    #[inline]
+    pub fn as_micros(&self) -> u64 {
+        self.0
+    }
+    /// The clock tick the timestamp falls into
+    #[inline]
+    pub fn instant(&self) -> Instant {
+        Instant(self.0 / (1_000_000 / TICKS_PER_SECOND as u64))
+    }
+    /// Saturates to zero if ``earlier`` is later than self
+    pub fn duration_since(&self, earlier: Timestamp) -> Duration {
+        Duration::from_micros(self.0.saturating_sub(earlier.0))
+    }
+    /// The first clock tick that does not start before the timestamp,
+    /// a timer for it does not expire too early
+    #[inline]
+    pub(crate) fn next_instant(&self) -> Instant {
+        let micros_per_tick = 1_000_000 / TICKS_PER_SECOND as u64;
+        Instant((self.0 + micros_per_tick - 1) / micros_per_tick)
+    }
+}
+
+impl Add<Duration> for Timestamp {
+    type Output = Timestamp;
+    fn add(self, duration: Duration) -> Timestamp {
+        Timestamp(self.0.saturating_add(duration.as_micros() as u64))
+    }
+}
+
+/// Rounds up so that a timer never expires too early
+pub(crate) fn duration_to_ticks(duration: Duration) -> u64 {
+    let nanos_per_tick = 1_000_000_000 / TICKS_PER_SECOND as u128;
+    ((duration.as_nanos() + nanos_per_tick - 1) / nanos_per_tick) as u64
+}
+fn ticks_to_duration(ticks: u64) -> Duration {
+    Duration::from_nanos(ticks * (1_000_000_000 / TICKS_PER_SECOND as u64))
+}
+
+/// Waits until ``duration`` has elapsed
+#[inline]
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep::until(Instant::now() + duration)
+}
+
+/// Waits until the given point in time
+#[inline]
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep::until(deadline)
+}
+
+/// Creates an interval that ticks every ``period``.
+/// The first tick completes after one period.
+#[inline]
+pub fn interval(period: Duration) -> Interval {
+    Interval {
+        next: Instant::now() + period,
+        period,
+    }
+}
+
+/// Polls ``future`` until it completes or ``duration`` elapsed
+pub fn timeout<F: Future>(future: F, duration: Duration) -> Timeout<F> {
+    Timeout {
+        future,
+        sleep: sleep(duration),
+    }
+}
+
+/// Future returned by [sleep] and [sleep_until]
+#[derive(Debug)]
+pub struct Sleep {
+    deadline: Instant,
+}
+
+impl Sleep {
+    #[inline]
+    fn until(deadline: Instant) -> Self {
+        Sleep { deadline }
+    }
+    #[inline]
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            Poll::Ready(())
+        } else {
+            // the executor keeps one timer per waker, polling again does not add one
+            Runtime::register_timer(self.deadline, cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+/// Ticks with a fixed period.
+/// If a tick is awaited too late, the following ticks complete immediately
+/// until the interval caught up with the schedule.
+#[derive(Debug)]
+pub struct Interval {
+    next: Instant,
+    period: Duration,
+}
+
+impl Interval {
+    /// Waits for the next tick of the interval
+    pub fn tick(&mut self) -> Sleep {
+        let deadline = self.next;
+        self.next += self.period;
+        Sleep::until(deadline)
+    }
+    #[inline]
+    pub fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+/// The error returned by [timeout] if the deadline was reached first
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Elapsed;
+
+/// Future returned by [timeout]
+#[pin_project]
+#[derive(Debug)]
+pub struct Timeout<F> {
+    #[pin]
+    future: F,
+    sleep: Sleep,
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(output) = this.future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        match Pin::new(this.sleep).poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "host"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_need_a_unit() {
+        assert_eq!(parse_duration("500us"), Some(Duration::from_micros(500)));
+        assert_eq!(parse_duration("20ms"), Some(Duration::from_millis(20)));
+        assert_eq!(parse_duration("1s"), Some(Duration::from_secs(1)));
+        assert_eq!(parse_duration("0ms"), Some(Duration::from_millis(0)));
+        assert_eq!(parse_duration("20"), None);
+        assert_eq!(parse_duration("ms"), None);
+        assert_eq!(parse_duration("20 ms"), None);
+        assert_eq!(parse_duration("20min"), None);
+        assert_eq!(parse_duration("-1s"), None);
+    }
+}
@@ -0,0 +1,180 @@
+//! Recorder for event pushes, waker registrations and task polls
+//! (requires the ``trace`` feature).
+//!
+//! Recording starts with [start] and writes into a ring buffer that the
+//! application provides, the oldest records are overwritten. [dump] writes
+//! the records to a resource, e.g. a serial port, and the ``trace_decoder``
+//! of the workspace renders them as a timeline:
+//! ```ignore
+//! static mut TRACE: [Record; 256] = [NO_RECORD; 256];
+//! trace::start(unsafe { &mut TRACE }).ok();
+//! ...
+//! trace::dump(&mut serial).await?;
+//! ```
+//!
+//! The dump is little endian: the header ``b"ERT"``, [FORMAT_VERSION], the
+//! number of records (u32) and the number of overwritten records (u32),
+//! followed by the records from old to new. A record has 8 bytes: the
+//! lower 32 bits of the [Timestamp] in µs, the [Kind], the executor slot
+//! (``0xff`` outside of executors) and a 16 bit argument (see [Kind]).
+use crate::device::interrupt::{self, CriticalSection};
+use crate::events::Event;
+use crate::io::{self, AsyncWrite, AsyncWriteExt};
+use crate::time::Timestamp;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+pub const FORMAT_VERSION: u8 = 1;
+/// Executor slot of records that are not made by an executor
+pub const NO_EXECUTOR: u8 = 0xff;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Kind {
+    /// ``events::push``, the argument is the event code
+    EventPush = 1,
+    /// A task registered a waker for an event, the argument is the event code
+    WakerRegistered = 2,
+    /// The argument is the lower 16 bits of the task id
+    PollStart = 3,
+    /// The task returned pending
+    PollEnd = 4,
+    /// The task finished
+    PollReady = 5,
+}
+
+/// One record of the dump format
+#[derive(Copy, Clone, Debug)]
+pub struct Record([u8; 8]);
+
+/// An empty slot of a trace buffer
+pub const NO_RECORD: Record = Record([0; 8]);
+
+struct Recorder {
+    buffer: &'static mut [Record],
+    /// Index of the next record
+    next: usize,
+    /// Records in the buffer
+    len: usize,
+    overwritten: u32,
+}
+
+static mut RECORDER: Option<Recorder> = None;
+/// Set by [start], cleared by [stop]
+static RECORDING: AtomicBool = AtomicBool::new(false);
+/// Set while [dump] writes the buffer, nothing is recorded meanwhile, so the
+/// dump does not trace itself and the buffer does not change under it
+static DUMPING: AtomicBool = AtomicBool::new(false);
+
+#[inline]
+fn recorder(_cs: &CriticalSection) -> &'static mut Option<Recorder> {
+    unsafe { &mut RECORDER }
+}
+
+/// Starts recording into ``buffer``, records of an earlier buffer are dropped.
+///
+/// The earlier buffer is still written while a [dump] is in progress, the
+/// new ``buffer`` is handed back then.
+pub fn start(buffer: &'static mut [Record]) -> Result<(), &'static mut [Record]> {
+    interrupt::free(move |cs| {
+        if DUMPING.load(Ordering::Acquire) {
+            return Err(buffer);
+        }
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        *recorder(cs) = Some(Recorder {
+            buffer,
+            next: 0,
+            len: 0,
+            overwritten: 0,
+        });
+        RECORDING.store(true, Ordering::Release);
+        Ok(())
+    })
+}
+
+/// Stops recording, the records stay for [dump]
+pub fn stop() {
+    RECORDING.store(false, Ordering::Release)
+}
+
+#[inline]
+pub(crate) fn record(kind: Kind, executor: usize, arg: u16) {
+    if !RECORDING.load(Ordering::Acquire) || DUMPING.load(Ordering::Acquire) {
+        return;
+    }
+    let timestamp = Timestamp::now().as_micros() as u32;
+    interrupt::free(|cs| {
+        if let Some(recorder) = recorder(cs) {
+            let mut record = [0; 8];
+            record[..4].copy_from_slice(&timestamp.to_le_bytes());
+            record[4] = kind as u8;
+            record[5] = executor.min(NO_EXECUTOR as usize) as u8;
+            record[6..].copy_from_slice(&arg.to_le_bytes());
+            let capacity = recorder.buffer.len();
+            recorder.buffer[recorder.next] = Record(record);
+            recorder.next = (recorder.next + 1) % capacity;
+            if recorder.len == capacity {
+                recorder.overwritten = recorder.overwritten.saturating_add(1);
+            } else {
+                recorder.len += 1;
+            }
+        }
+    })
+}
+
+/// The 16 bit argument of event records
+pub(crate) fn event_code(event: &Event) -> u16 {
+    match event {
+        Event::Timer => 0x0001,
+        Event::DeviceInterrupt => 0x0002,
+        Event::ExternalInterrupt(exti) => 0x1000 | exti.trace_code(),
+        Event::User(index) => 0x2000 | *index as u16,
+    }
+}
+
+/// Clears [DUMPING] when the dump finishes or is dropped
+struct Dumping;
+
+impl Drop for Dumping {
+    fn drop(&mut self) {
+        DUMPING.store(false, Ordering::Release)
+    }
+}
+
+/// Writes all records to ``writer``, recording pauses meanwhile.
+///
+/// Fails with ``WouldBlock`` while another dump is in progress.
+pub async fn dump<W: AsyncWrite + Unpin>(writer: &mut W) -> Result<(), io::Error> {
+    if DUMPING.swap(true, Ordering::AcqRel) {
+        return Err(io::Error::WouldBlock);
+    }
+    let _dumping = Dumping;
+    write_records(writer).await
+}
+
+async fn write_records<W: AsyncWrite + Unpin>(writer: &mut W) -> Result<(), io::Error> {
+    // neither records nor [start] touch the buffer while it is written
+    let (buffer, next, len, overwritten) = interrupt::free(|cs| match recorder(cs) {
+        Some(recorder) => (
+            &recorder.buffer[..],
+            recorder.next,
+            recorder.len,
+            recorder.overwritten,
+        ),
+        None => (&[][..], 0, 0, 0),
+    });
+    let mut header = [0; 12];
+    header[..3].copy_from_slice(b"ERT");
+    header[3] = FORMAT_VERSION;
+    header[4..8].copy_from_slice(&(len as u32).to_le_bytes());
+    header[8..].copy_from_slice(&overwritten.to_le_bytes());
+    writer.write_all(&header).await?;
+    // the oldest record is at ``next`` once the buffer is full
+    let first = if len == buffer.len() { next } else { 0 };
+    for i in 0..len {
+        writer
+            .write_all(&buffer[(first + i) % buffer.len()].0)
+            .await?;
+    }
+    writer.flush().await
+}
@@ -0,0 +1,170 @@
+//! The independent hardware watchdog, owned by the runtime.
+//!
+//! Once started, [Runtime::run](crate::Runtime::run) feeds the watchdog
+//! between executor passes, but only while every critical task checked in
+//! within its deadline. A task that hangs in a poll stops the executor and
+//! a critical task that stops checking in stops the feeding, both reset the
+//! device after the watchdog ``timeout``. [last_reset_reason](crate::supervisor::last_reset_reason)
+//! reports ``ResetReason::Watchdog`` afterwards.
+//!
+//! The watchdog is usually started by the ``watchdog`` entry of the sys
+//! section in the ``device_config``:
+//! ```ignore
+//! "sys": { "watchdog": { "timeout": [2, "s"] } }
+//! ```
+//! Critical tasks register for a check-in:
+//! ```ignore
+//! let check_in = watchdog::register(Duration::from_millis(500))?;
+//! loop {
+//!     sample().await;
+//!     check_in.check_in();
+//! }
+//! ```
+use crate::device::interrupt::{self, Mutex};
+use crate::executor::Executor;
+use crate::time::Instant;
+use crate::{device, RuntimeError};
+use core::cell::RefCell;
+use core::task::{RawWaker, RawWakerVTable, Waker};
+use core::time::Duration;
+
+/// Maximum number of critical tasks
+const MAX_CRITICAL: usize = 8;
+
+struct CriticalSlot {
+    deadline: Duration,
+    last_check_in: Instant,
+}
+
+struct State {
+    /// The runtime checks the critical tasks and feeds the watchdog this often
+    period: Duration,
+    next_feed: Instant,
+    /// Set after a missed check-in was logged, so it is logged once
+    starving: bool,
+}
+
+const FREE: Option<CriticalSlot> = None;
+static SLOTS: Mutex<RefCell<[Option<CriticalSlot>; MAX_CRITICAL]>> =
+    Mutex::new(RefCell::new([FREE; MAX_CRITICAL]));
+static mut STATE: Option<State> = None;
+
+/// Starts the watchdog, it resets the device if it is not fed for ``timeout``.
+/// The watchdog cannot be stopped again.
+pub fn start(timeout: Duration) {
+    device::start_watchdog(timeout);
+    unsafe {
+        STATE = Some(State {
+            // the watchdog clock is inaccurate, so feed it early
+            period: timeout / 4,
+            next_feed: Instant::now(),
+            starving: false,
+        })
+    }
+}
+
+/// Registers a critical task that has to call [CheckIn::check_in] at least
+/// every ``deadline``. The registration ends when the CheckIn is dropped.
+pub fn register(deadline: Duration) -> Result<CheckIn, RuntimeError> {
+    interrupt::free(|cs| {
+        let mut slots = SLOTS.borrow(cs).borrow_mut();
+        let (index, slot) = slots
+            .iter_mut()
+            .enumerate()
+            .find(|(_, slot)| slot.is_none())
+            .ok_or(RuntimeError::TooManyCriticalTasks)?;
+        *slot = Some(CriticalSlot {
+            deadline,
+            last_check_in: Instant::now(),
+        });
+        Ok(CheckIn { slot: index })
+    })
+}
+
+/// The registration of a critical task
+pub struct CheckIn {
+    slot: usize,
+}
+
+impl CheckIn {
+    /// Tells the runtime that the task is alive
+    pub fn check_in(&self) {
+        interrupt::free(|cs| {
+            if let Some(slot) = &mut SLOTS.borrow(cs).borrow_mut()[self.slot] {
+                slot.last_check_in = Instant::now();
+            }
+        })
+    }
+}
+
+impl Drop for CheckIn {
+    fn drop(&mut self) {
+        interrupt::free(|cs| SLOTS.borrow(cs).borrow_mut()[self.slot] = None)
+    }
+}
+
+/// Stops servicing the watchdog and drops all registrations
+#[cfg(feature = "host")]
+pub(crate) fn reset() {
+    unsafe { STATE = None };
+    interrupt::free(|cs| {
+        for slot in SLOTS.borrow(cs).borrow_mut().iter_mut() {
+            *slot = None
+        }
+    })
+}
+
+/// Feeds the watchdog if it is due and all critical tasks are alive.
+/// A timer makes sure the runtime wakes up for the next feed.
+pub(crate) fn service(executor: &mut Executor) {
+    let state = match unsafe { STATE.as_mut() } {
+        Some(state) => state,
+        None => return,
+    };
+    let now = Instant::now();
+    if now < state.next_feed {
+        return;
+    }
+    match missed_check_in(now) {
+        None => {
+            device::feed_watchdog();
+            state.starving = false;
+        }
+        Some(slot) => {
+            if !state.starving {
+                log::error!(
+                    "critical task {} missed its check-in, the watchdog resets the device",
+                    slot
+                );
+                state.starving = true;
+            }
+        }
+    }
+    state.next_feed = now + state.period;
+    executor.register_timer(state.next_feed, &noop_waker());
+}
+
+/// The first critical task whose deadline passed
+fn missed_check_in(now: Instant) -> Option<usize> {
+    interrupt::free(|cs| {
+        SLOTS
+            .borrow(cs)
+            .borrow()
+            .iter()
+            .position(|slot| match slot {
+                Some(slot) => now.duration_since(slot.last_check_in) > slot.deadline,
+                None => false,
+            })
+    })
+}
+
+/// The feeding timer only has to wake the runtime, not a task
+static NOOP_WAKER_VTABLE: RawWakerVTable = RawWakerVTable::new(clone_noop, noop, noop, noop);
+
+fn noop_waker() -> Waker {
+    unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &NOOP_WAKER_VTABLE)) }
+}
+unsafe fn clone_noop(_: *const ()) -> RawWaker {
+    RawWaker::new(core::ptr::null(), &NOOP_WAKER_VTABLE)
+}
+unsafe fn noop(_: *const ()) {}
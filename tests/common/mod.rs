@@ -0,0 +1,36 @@
+//! Fixtures shared by the integration tests
+#![allow(dead_code)]
+use embedded_rust::resources::{Resource, UserEvent};
+use embedded_rust::Runtime;
+
+/// Initializes the runtime of a simulation without resources
+pub fn init() -> &'static mut Runtime {
+    init_with(Vec::new(), Vec::new(), Vec::new())
+}
+
+/// Initializes the runtime of a simulation with sys resources, input pins
+/// and user events. There is no heap to set up and the clock is simulated.
+pub fn init_with(
+    sys: Vec<&'static mut dyn Resource>,
+    input_pins: Vec<&'static mut dyn Resource>,
+    user_events: Vec<UserEvent>,
+) -> &'static mut Runtime {
+    Runtime::init(
+        0,
+        0,
+        sys.leak(),
+        input_pins.leak(),
+        &mut [],
+        &mut [],
+        &mut [],
+        &mut [],
+        &mut [],
+        user_events.leak(),
+    )
+    .unwrap()
+}
+
+/// Resources are static, they stay alive until the test binary exits
+pub fn leak(resource: impl Resource + 'static) -> &'static mut dyn Resource {
+    Box::leak(Box::new(resource))
+}
@@ -0,0 +1,207 @@
+//! Executor tests on the host, run with
+//! ``cargo test --no-default-features --features host``
+#![cfg(all(feature = "host", not(feature = "task-pool")))]
+use core::future::{poll_fn, Future};
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::Poll;
+use core::time::Duration;
+use embedded_rust::device::host::Simulation;
+use embedded_rust::device::interrupt::Nr;
+use embedded_rust::io::AsyncReadExt;
+use embedded_rust::resources::{SysResource, UserEvent};
+use embedded_rust::time::Timestamp;
+use embedded_rust::*;
+
+mod common;
+use common::{init, init_with, leak};
+
+#[test]
+fn timeouts_in_a_loop_do_not_pile_up() {
+    let _simulation = Simulation::start();
+    let runtime = init_with(vec![], vec![], vec![UserEvent::new(0, "tick")]);
+    let mut tick = Runtime::get().get_resource("event:user/tick").unwrap();
+    let waiter = Task::new(async move {
+        let mut buf = [0; 9];
+        // every iteration leaves a timer and an event waker behind
+        for _ in 0..200 {
+            let read = time::timeout(tick.read(&mut buf), Duration::from_millis(1)).await;
+            assert!(read.is_err());
+        }
+    })
+    .spawn();
+    runtime.run_for(Duration::from_millis(300));
+    assert!(waiter.is_finished());
+}
+
+#[test]
+fn a_task_is_queued_once_however_often_it_is_woken() {
+    static POLLS: AtomicU32 = AtomicU32::new(0);
+    let _simulation = Simulation::start();
+    let runtime = init();
+    let task = Task::new(poll_fn(|cx| {
+        if POLLS.fetch_add(1, Ordering::SeqCst) > 0 {
+            return Poll::Ready(());
+        }
+        for _ in 0..100 {
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }))
+    .spawn();
+    runtime.run_until_idle();
+    assert!(task.is_finished());
+    assert_eq!(POLLS.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn a_task_awaits_many_timers_at_once() {
+    let _simulation = Simulation::start();
+    let runtime = init();
+    let task = Task::new(async {
+        let mut sleeps: Vec<_> = (1..=12)
+            .map(|millis| Box::pin(time::sleep(Duration::from_millis(millis))))
+            .collect();
+        // every poll registers the timers of all pending sleeps
+        poll_fn(|cx| {
+            sleeps.retain_mut(|sleep| sleep.as_mut().poll(cx).is_pending());
+            if sleeps.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    })
+    .spawn();
+    runtime.run_until_idle();
+    assert!(task.is_finished());
+    assert_eq!(Timestamp::now(), Timestamp::from_micros(12_000));
+}
+
+#[test]
+fn a_task_subscribes_to_an_event_many_times() {
+    static READS: AtomicU32 = AtomicU32::new(0);
+    let _simulation = Simulation::start();
+    let runtime = init_with(vec![], vec![], vec![UserEvent::new(0, "tick")]);
+    let mut subscribers: Vec<_> = (0..20)
+        .map(|_| {
+            Runtime::get()
+                .get_resource("event:user/tick")
+                .unwrap()
+                .into_event()
+                .unwrap()
+        })
+        .collect();
+    let tick = subscribers[0].id();
+    let task = Task::new(async move {
+        let mut reads: Vec<_> = subscribers
+            .iter_mut()
+            .map(|subscriber| Box::pin(subscriber.next()))
+            .collect();
+        // every poll registers a waker for each pending read
+        poll_fn(|cx| {
+            reads.retain_mut(|read| match read.as_mut().poll(cx) {
+                Poll::Ready(timestamp) => {
+                    timestamp.unwrap();
+                    READS.fetch_add(1, Ordering::SeqCst);
+                    false
+                }
+                Poll::Pending => true,
+            });
+            if reads.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    })
+    .spawn();
+    Task::new(async move {
+        time::sleep(Duration::from_millis(1)).await;
+        tick.raise().unwrap();
+    })
+    .spawn();
+    runtime.run_until_idle();
+    assert!(task.is_finished());
+    assert_eq!(READS.load(Ordering::SeqCst), 20);
+}
+
+static URGENT: InterruptExecutor = InterruptExecutor::new();
+
+#[derive(Clone, Copy)]
+struct SoftwareInterrupt;
+unsafe impl Nr for SoftwareInterrupt {
+    fn nr(&self) -> u8 {
+        3
+    }
+}
+
+#[test]
+fn interrupt_executor_tasks_are_joined_from_thread_mode() {
+    let _simulation = Simulation::start();
+    let runtime = init();
+    assert_eq!(
+        URGENT.start(SoftwareInterrupt, 16),
+        Err(RuntimeError::InvalidPriority)
+    );
+    URGENT.start(SoftwareInterrupt, 1).unwrap();
+    let urgent = URGENT.spawn(SendTask::new(async {
+        time::sleep(Duration::from_millis(10)).await;
+        5
+    }));
+    let joiner = Task::new(async move { assert_eq!(urgent.await, Ok(5)) }).spawn();
+    runtime.run_for(Duration::from_millis(20));
+    assert!(joiner.is_finished());
+}
+
+#[test]
+fn sys_tables_are_read_while_the_executor_polls() {
+    let _simulation = Simulation::start();
+    let runtime = init_with(vec![leak(SysResource::new_tasks())], vec![], vec![]);
+    let mut table = Runtime::get()
+        .get_resource("sys:sys/tasks")
+        .unwrap()
+        .into_sys()
+        .unwrap();
+    let sleeper = Task::new(time::sleep(Duration::from_millis(5))).spawn();
+    let reader = Task::new(async move {
+        // the tasks are listed after the first pass of the executor
+        time::sleep(Duration::from_millis(1)).await;
+        let mut buf = [0; 256];
+        let text = table.read_str(&mut buf).await.unwrap();
+        // the header, the reader and the sleeper
+        assert_eq!(text.lines().count(), 3);
+    })
+    .spawn();
+    runtime.run_for(Duration::from_millis(10));
+    assert!(reader.is_finished());
+    assert!(sleeper.is_finished());
+}
+
+#[test]
+fn blocked_jobs_miss_their_deadline() {
+    let _simulation = Simulation::start();
+    let runtime = init_with(vec![leak(SysResource::new_deadlines())], vec![], vec![]);
+    let mut table = Runtime::get()
+        .get_resource("sys:sys/deadlines")
+        .unwrap()
+        .into_sys()
+        .unwrap();
+    Task::new(async {
+        next_period().await;
+        // the second job never ends
+        core::future::pending::<()>().await
+    })
+    .with_deadline(Duration::from_millis(10), Duration::from_millis(2))
+    .spawn();
+    let reader = Task::new(async move {
+        time::sleep(Duration::from_millis(15)).await;
+        let mut buf = [0; 128];
+        let text = table.read_str(&mut buf).await.unwrap();
+        assert_eq!(text.lines().next(), Some("misses 1"));
+    })
+    .spawn();
+    runtime.run_for(Duration::from_millis(20));
+    assert!(reader.is_finished());
+}
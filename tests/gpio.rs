@@ -0,0 +1,138 @@
+//! Gpio events on the host, run with
+//! ``cargo test --no-default-features --features host``
+#![cfg(all(feature = "host", not(feature = "task-pool")))]
+use core::future::Future;
+use core::pin::Pin as StdPin;
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::task::{Context, Poll};
+use core::time::Duration;
+use embedded_hal::digital::v2;
+use embedded_rust::device::host::{trigger, Simulation};
+use embedded_rust::device::ExtiEvent;
+use embedded_rust::io::AsyncReadExt;
+use embedded_rust::resources::{InputPin, Pin, ResourceError};
+use embedded_rust::*;
+
+mod common;
+use common::{init_with, leak};
+
+/// A pin that stays high, the edges carry the level sampled by the
+/// interrupt handler
+struct HighPin;
+
+impl v2::InputPin for HighPin {
+    type Error = &'static str;
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+/// Counts the polls of the wrapped future
+struct CountPolls<'c, F> {
+    future: F,
+    polls: &'c AtomicU32,
+}
+
+impl<F: Future + Unpin> Future for CountPolls<'_, F> {
+    type Output = F::Output;
+    fn poll(mut self: StdPin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        self.polls.fetch_add(1, Ordering::SeqCst);
+        StdPin::new(&mut self.future).poll(cx)
+    }
+}
+
+#[test]
+fn bounces_wake_no_subscriber() {
+    static POLLS: AtomicU32 = AtomicU32::new(0);
+    let _simulation = Simulation::start();
+    let pin = Pin::from_str("pa0").unwrap();
+    let runtime = init_with(
+        vec![],
+        vec![leak(
+            InputPin::new(pin, HighPin).with_debounce(Duration::from_millis(5)),
+        )],
+        vec![],
+    );
+    let mut edges = Runtime::get().get_resource("event:gpio/pa0").unwrap();
+    Task::new(async move {
+        let mut buf = [0; 9];
+        loop {
+            let read = CountPolls {
+                future: edges.read(&mut buf),
+                polls: &POLLS,
+            };
+            read.await.unwrap();
+        }
+    })
+    .spawn();
+    Task::new(async move {
+        // an edge and two bounces 1 ms apart
+        for delay in [10, 1, 1] {
+            time::sleep(Duration::from_millis(delay)).await;
+            trigger(ExtiEvent::Gpio(pin), false);
+        }
+    })
+    .spawn();
+    runtime.run_for(Duration::from_millis(20));
+    // subscribe, read the edge, wait for the next one
+    assert_eq!(POLLS.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn edges_carry_the_level_of_the_interrupt() {
+    let _simulation = Simulation::start();
+    let pin = Pin::from_str("pa1").unwrap();
+    let runtime = init_with(vec![], vec![leak(InputPin::new(pin, HighPin))], vec![]);
+    let mut falling = Runtime::get()
+        .get_resource("event:gpio/pa1?edge=falling")
+        .unwrap();
+    let reader = Task::new(async move {
+        let mut buf = [0; 9];
+        falling.read(&mut buf).await.unwrap();
+    })
+    .spawn();
+    Task::new(async move {
+        time::sleep(Duration::from_millis(1)).await;
+        trigger(ExtiEvent::Gpio(pin), false);
+    })
+    .spawn();
+    runtime.run_for(Duration::from_millis(5));
+    assert!(reader.is_finished());
+}
+
+#[test]
+fn percent_reads_are_text_unless_fmt_selects_binary() {
+    let _simulation = Simulation::start();
+    let pin = Pin::from_str("pa2").unwrap();
+    let runtime = init_with(vec![], vec![leak(InputPin::new(pin, HighPin))], vec![]);
+    let mut text = Runtime::get().get_resource("percent:gpio/pa2").unwrap();
+    let mut binary = Runtime::get()
+        .get_resource("percent:gpio/pa2?fmt=bin")
+        .unwrap();
+    let mut handle = Runtime::get()
+        .get_resource("percent:gpio/pa2")
+        .unwrap()
+        .into_percent()
+        .unwrap();
+    assert!(matches!(
+        Runtime::get()
+            .get_resource("percent:gpio/pa2?fmt=text")
+            .unwrap()
+            .into_percent(),
+        Err(ResourceError::ConversionError)
+    ));
+    let reader = Task::new(async move {
+        let mut buf = [0; 8];
+        let len = text.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], b"1");
+        let len = binary.read(&mut buf).await.unwrap();
+        assert_eq!(&buf[..len], &1.0f32.to_ne_bytes());
+        assert_eq!(handle.get().await.unwrap(), 1.0);
+    })
+    .spawn();
+    runtime.run_until_idle();
+    assert!(reader.is_finished());
+}
@@ -0,0 +1,124 @@
+//! The simulated device, run with
+//! ``cargo test --no-default-features --features host``
+#![cfg(all(feature = "host", not(feature = "task-pool")))]
+use core::sync::atomic::{AtomicU32, Ordering};
+use core::time::Duration;
+use embedded_hal::digital::v2;
+use embedded_rust::device::host::{advance_clock, trigger, Simulation};
+use embedded_rust::device::ExtiEvent;
+use embedded_rust::resources::{InputPin, Pin, UserEvent};
+use embedded_rust::supervisor::{RestartPolicy, Supervisor};
+use embedded_rust::time::Timestamp;
+use embedded_rust::*;
+
+mod common;
+use common::{init, init_with, leak};
+
+/// A pin that stays low, its edges are falling
+struct LowPin;
+
+impl v2::InputPin for LowPin {
+    type Error = &'static str;
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+#[test]
+fn run_until_idle_jumps_to_the_timers() {
+    let _simulation = Simulation::start();
+    let runtime = init();
+    assert_eq!(Timestamp::now(), Timestamp::from_micros(0));
+    let sleeper = Task::new(time::sleep(Duration::from_secs(1))).spawn();
+    runtime.run_until_idle();
+    assert!(sleeper.is_finished());
+    assert_eq!(Timestamp::now(), Timestamp::from_micros(1_000_000));
+}
+
+#[test]
+fn run_until_idle_returns_while_tasks_wait_for_events() {
+    let _simulation = Simulation::start();
+    let runtime = init_with(vec![], vec![], vec![UserEvent::new(0, "never")]);
+    let mut never = Runtime::get()
+        .get_resource("event:user/never")
+        .unwrap()
+        .into_event()
+        .unwrap();
+    let waiter = Task::new(async move {
+        never.next().await.unwrap();
+    })
+    .spawn();
+    let sleeper = Task::new(time::sleep(Duration::from_millis(10))).spawn();
+    runtime.run_until_idle();
+    assert!(sleeper.is_finished());
+    assert!(!waiter.is_finished());
+    assert_eq!(Timestamp::now(), Timestamp::from_micros(10_000));
+}
+
+#[test]
+fn run_for_stops_periodic_tasks_at_the_end() {
+    static TICKS: AtomicU32 = AtomicU32::new(0);
+    let _simulation = Simulation::start();
+    let runtime = init();
+    let ticker = Task::new(async {
+        loop {
+            time::sleep(Duration::from_millis(10)).await;
+            TICKS.fetch_add(1, Ordering::SeqCst);
+        }
+    })
+    .spawn();
+    runtime.run_for(Duration::from_millis(105));
+    assert!(!ticker.is_finished());
+    assert_eq!(TICKS.load(Ordering::SeqCst), 10);
+    assert_eq!(Timestamp::now(), Timestamp::from_micros(105_000));
+}
+
+#[test]
+fn advance_clock_fires_the_alarm_once_it_is_reached() {
+    let _simulation = Simulation::start();
+    init();
+    let sleeper = Task::new(time::sleep(Duration::from_millis(5))).spawn();
+    // a run without time passing sets the alarm
+    Runtime::get().run_for(Duration::from_millis(0));
+    advance_clock(Duration::from_millis(4));
+    Runtime::get().run_for(Duration::from_millis(0));
+    assert!(!sleeper.is_finished());
+    advance_clock(Duration::from_millis(1));
+    Runtime::get().run_for(Duration::from_millis(0));
+    assert!(sleeper.is_finished());
+    assert_eq!(Timestamp::now(), Timestamp::from_micros(5_000));
+}
+
+#[test]
+fn trigger_stamps_edges_with_the_simulated_clock() {
+    let _simulation = Simulation::start();
+    let pin = Pin::from_str("pb0").unwrap();
+    init_with(vec![], vec![leak(InputPin::new(pin, LowPin))], vec![]);
+    let mut edges = Runtime::get()
+        .get_resource("event:gpio/pb0")
+        .unwrap()
+        .into_event()
+        .unwrap();
+    let reader = Task::new(async move {
+        assert_eq!(edges.next().await.unwrap(), Timestamp::from_micros(7_000));
+    })
+    .spawn();
+    // subscribe before the edge
+    Runtime::get().run_for(Duration::from_millis(0));
+    advance_clock(Duration::from_millis(7));
+    trigger(ExtiEvent::Gpio(pin), false);
+    Runtime::get().run_for(Duration::from_millis(0));
+    assert!(reader.is_finished());
+}
+
+#[test]
+#[should_panic(expected = "device reset")]
+fn a_reset_fails_the_run() {
+    let _simulation = Simulation::start();
+    let runtime = init();
+    Supervisor::new(|| Task::new(async {}), RestartPolicy::Reset).spawn();
+    runtime.run_until_idle();
+}
@@ -0,0 +1,282 @@
+//! Renders a dump of ``embedded_rust::trace`` as a timeline.
+//!
+//! ```text
+//! trace_decoder [dump file]
+//! ```
+//! Reads the dump from stdin without a file. The format is described in the
+//! trace module of the runtime.
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::process;
+
+const MAGIC: &[u8; 3] = b"ERT";
+const FORMAT_VERSION: u8 = 1;
+const HEADER_LEN: usize = 12;
+const RECORD_LEN: usize = 8;
+const NO_EXECUTOR: u8 = 0xff;
+
+#[derive(Debug)]
+enum Error {
+    Io(io::Error),
+    NoDump,
+    Version(u8),
+    Truncated { expected: usize, found: usize },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::NoDump => write!(f, "not a trace dump"),
+            Error::Version(version) => write!(
+                f,
+                "dump format {} is not supported, expected {}",
+                version, FORMAT_VERSION
+            ),
+            Error::Truncated { expected, found } => {
+                write!(f, "dump has {} of {} records", found, expected)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+struct Record {
+    /// Microseconds since boot, unwrapped from the 32 bit timestamps
+    time: u64,
+    kind: u8,
+    executor: u8,
+    arg: u16,
+}
+
+struct Dump {
+    overwritten: u32,
+    records: Vec<Record>,
+}
+
+fn parse(bytes: &[u8]) -> Result<Dump, Error> {
+    if bytes.len() < HEADER_LEN || &bytes[..3] != MAGIC {
+        return Err(Error::NoDump);
+    }
+    if bytes[3] != FORMAT_VERSION {
+        return Err(Error::Version(bytes[3]));
+    }
+    let count = u32_at(bytes, 4) as usize;
+    let overwritten = u32_at(bytes, 8);
+    let body = &bytes[HEADER_LEN..];
+    if body.len() < count * RECORD_LEN {
+        return Err(Error::Truncated {
+            expected: count,
+            found: body.len() / RECORD_LEN,
+        });
+    }
+    let mut records = Vec::with_capacity(count);
+    let mut epoch = 0u64;
+    let mut last = 0;
+    for record in body.chunks_exact(RECORD_LEN).take(count) {
+        let time = u32_at(record, 0);
+        // the records are in order, so a smaller timestamp wrapped around
+        if time < last {
+            epoch += 1 << 32;
+        }
+        last = time;
+        records.push(Record {
+            time: epoch + time as u64,
+            kind: record[4],
+            executor: record[5],
+            arg: u16::from_le_bytes([record[6], record[7]]),
+        });
+    }
+    Ok(Dump {
+        overwritten,
+        records,
+    })
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    let mut word = [0; 4];
+    word.copy_from_slice(&bytes[offset..offset + 4]);
+    u32::from_le_bytes(word)
+}
+
+fn executor_name(executor: u8) -> String {
+    match executor {
+        0 => "thread".to_string(),
+        NO_EXECUTOR => "isr".to_string(),
+        slot => format!("irq {}", slot),
+    }
+}
+
+fn event_name(code: u16) -> String {
+    match code >> 12 {
+        0 => match code {
+            0x0001 => "timer".to_string(),
+            0x0002 => "device interrupt".to_string(),
+            code => format!("event {:#06x}", code),
+        },
+        1 => match code & 0x0fff {
+            0x100 => "exti pvd".to_string(),
+            0x101 => "exti rtc alarm".to_string(),
+            0x102 => "exti usb wakeup".to_string(),
+            0x103 => "exti ethernet wakeup".to_string(),
+            pin if pin < 0x100 => {
+                let channel = (b'a' + (pin >> 4) as u8) as char;
+                format!("gpio p{}{}", channel, pin & 0xf)
+            }
+            line => format!("exti {:#05x}", line),
+        },
+        2 => format!("user event {}", code & 0x0fff),
+        _ => format!("event {:#06x}", code),
+    }
+}
+
+fn print_timeline(dump: &Dump) {
+    if dump.overwritten > 0 {
+        println!("({} older records were overwritten)", dump.overwritten);
+    }
+    let start = match dump.records.first() {
+        Some(record) => record.time,
+        None => {
+            println!("no records");
+            return;
+        }
+    };
+    // poll start per executor slot: (task, time)
+    let mut polls: Vec<Option<(u16, u64)>> = vec![None; 256];
+    for record in &dump.records {
+        let description = match record.kind {
+            1 => format!("push     {}", event_name(record.arg)),
+            2 => format!("wait     {}", event_name(record.arg)),
+            3 => {
+                polls[record.executor as usize] = Some((record.arg, record.time));
+                format!("poll     task {}", record.arg)
+            }
+            4 | 5 => {
+                let state = if record.kind == 4 {
+                    "pending"
+                } else {
+                    "ready  "
+                };
+                match polls[record.executor as usize].take() {
+                    Some((task, since)) if task == record.arg => format!(
+                        "{}  task {} after {} µs",
+                        state,
+                        record.arg,
+                        record.time - since
+                    ),
+                    _ => format!("{}  task {}", state, record.arg),
+                }
+            }
+            kind => format!("unknown record {} ({:#06x})", kind, record.arg),
+        };
+        println!(
+            "{:>12.3} ms  [{:>6}]  {}",
+            (record.time - start) as f64 / 1000.0,
+            executor_name(record.executor),
+            description
+        );
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let mut bytes = Vec::new();
+    match env::args_os().nth(1) {
+        Some(path) => bytes = fs::read(path)?,
+        None => {
+            io::stdin().read_to_end(&mut bytes)?;
+        }
+    }
+    let dump = parse(&bytes)?;
+    print_timeline(&dump);
+    Ok(())
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("trace_decoder: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(count: u32, overwritten: u32, records: &[(u32, u8, u8, u16)]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        bytes.extend_from_slice(&count.to_le_bytes());
+        bytes.extend_from_slice(&overwritten.to_le_bytes());
+        for &(time, kind, executor, arg) in records {
+            bytes.extend_from_slice(&time.to_le_bytes());
+            bytes.push(kind);
+            bytes.push(executor);
+            bytes.extend_from_slice(&arg.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn parses_records() {
+        let bytes = dump(2, 7, &[(10, 3, 0, 42), (25, 5, NO_EXECUTOR, 42)]);
+        let dump = parse(&bytes).unwrap();
+        assert_eq!(dump.overwritten, 7);
+        assert_eq!(dump.records.len(), 2);
+        let record = &dump.records[1];
+        assert_eq!(
+            (record.time, record.kind, record.executor, record.arg),
+            (25, 5, NO_EXECUTOR, 42)
+        );
+    }
+
+    #[test]
+    fn unwraps_timestamps() {
+        let bytes = dump(3, 0, &[(u32::MAX - 5, 1, 0, 1), (4, 1, 0, 1), (3, 1, 0, 1)]);
+        let times: Vec<u64> = parse(&bytes)
+            .unwrap()
+            .records
+            .iter()
+            .map(|record| record.time)
+            .collect();
+        assert_eq!(
+            times,
+            vec![u32::MAX as u64 - 5, (1 << 32) + 4, (2 << 32) + 3]
+        );
+    }
+
+    #[test]
+    fn ignores_bytes_after_the_records() {
+        let mut bytes = dump(1, 0, &[(1, 1, 0, 1)]);
+        bytes.extend_from_slice(&[0; 5]);
+        assert_eq!(parse(&bytes).unwrap().records.len(), 1);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(matches!(parse(b"ERT"), Err(Error::NoDump)));
+        assert!(matches!(parse(&[0; HEADER_LEN]), Err(Error::NoDump)));
+        let mut bytes = dump(0, 0, &[]);
+        bytes[3] = FORMAT_VERSION + 1;
+        assert!(matches!(parse(&bytes), Err(Error::Version(2))));
+    }
+
+    #[test]
+    fn rejects_truncated_dumps() {
+        let mut bytes = dump(2, 0, &[(1, 1, 0, 1), (2, 1, 0, 1)]);
+        bytes.truncate(bytes.len() - 1);
+        assert!(matches!(
+            parse(&bytes),
+            Err(Error::Truncated {
+                expected: 2,
+                found: 1
+            })
+        ));
+    }
+}